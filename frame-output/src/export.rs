@@ -0,0 +1,58 @@
+use std::{
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::Path,
+};
+
+use embedded_graphics::prelude::IntoStorage;
+use image::ImageResult;
+
+use crate::{FrameOutput, from_rgba};
+
+impl FrameOutput {
+	pub fn save_png(&self, path: &Path) -> ImageResult<()> {
+		self.buffer.save_with_format(path, image::ImageFormat::Png)
+	}
+
+	/// Write the frame as binary PPM (P6).
+	pub fn write_ppm<W: Write>(&self, mut w: W) -> io::Result<()> {
+		write!(w, "P6\n{} {}\n255\n", self.buffer.width(), self.buffer.height())?;
+		for pixel in self.buffer.pixels() {
+			w.write_all(&pixel.0[..3])?;
+		}
+		w.flush()
+	}
+
+	pub fn save_ppm(&self, path: &Path) -> io::Result<()> {
+		self.write_ppm(BufWriter::new(File::create(path)?))
+	}
+
+	/// Pixels in the display's native format: row-major big-endian RGB565, two bytes per pixel.
+	pub fn to_rgb565(&self) -> Vec<u8> {
+		self.buffer
+			.pixels()
+			.flat_map(|pixel| from_rgba(pixel).into_storage().to_be_bytes())
+			.collect()
+	}
+
+	pub fn save_rgb565(&self, path: &Path) -> io::Result<()> {
+		std::fs::write(path, self.to_rgb565())
+	}
+}
+
+#[test]
+fn test_export() {
+	use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+
+	let mut disp = FrameOutput::new(2, 1);
+	disp.clear(Rgb565::BLACK).unwrap();
+	disp.fill_solid(
+		&Rectangle::new(Point::new(1, 0), Size::new(1, 1)),
+		Rgb565::new(31, 0, 1),
+	)
+	.unwrap();
+	assert_eq!(disp.to_rgb565(), [0, 0, 0xf8, 0x01]);
+	let mut ppm = vec![];
+	disp.write_ppm(&mut ppm).unwrap();
+	assert_eq!(ppm, b"P6\n2 1\n255\n\0\0\0\xf8\0\x08");
+}
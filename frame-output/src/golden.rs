@@ -0,0 +1,98 @@
+use std::{env, fmt, path::Path, path::PathBuf};
+
+use image::{ImageError, ImageFormat, Rgba, RgbaImage};
+
+use crate::FrameOutput;
+
+/// If set, [`FrameOutput::compare_golden`] overwrites the golden image instead of comparing.
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+#[derive(Debug)]
+pub enum GoldenError {
+	Image(ImageError),
+	/// `pixels` differ. The rendered frame was saved to `actual`, the diff image to `diff`.
+	Mismatch {
+		pixels: usize,
+		actual: PathBuf,
+		diff: PathBuf,
+	},
+}
+
+impl fmt::Display for GoldenError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			GoldenError::Image(e) => write!(f, "{e}"),
+			GoldenError::Mismatch { pixels, actual, diff } => write!(
+				f,
+				"{pixels} pixels differ (actual: {}, diff: {})",
+				actual.display(),
+				diff.display()
+			),
+		}
+	}
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<ImageError> for GoldenError {
+	fn from(e: ImageError) -> Self {
+		GoldenError::Image(e)
+	}
+}
+
+impl FrameOutput {
+	/// Compare pixel by pixel with the PNG at `path`.
+	/// On mismatch `<name>.actual.png` and `<name>.diff.png` are written next to it.
+	pub fn compare_golden(&self, path: &Path) -> Result<(), GoldenError> {
+		if env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+			self.save_png(path)?;
+			return Ok(());
+		}
+		let golden = image::open(path)?.into_rgba8();
+		let (pixels, diff) = diff_image(&golden, &self.buffer);
+		if pixels == 0 {
+			return Ok(());
+		}
+		let actual = path.with_extension("actual.png");
+		let diff_path = path.with_extension("diff.png");
+		self.save_png(&actual)?;
+		diff.save_with_format(&diff_path, ImageFormat::Png)?;
+		Err(GoldenError::Mismatch {
+			pixels,
+			actual,
+			diff: diff_path,
+		})
+	}
+}
+
+/// Mark differing pixels red on top of a dimmed copy of `expected`.
+/// Returns the number of differing pixels and the diff image.
+pub fn diff_image(expected: &RgbaImage, actual: &RgbaImage) -> (usize, RgbaImage) {
+	let width = expected.width().max(actual.width());
+	let height = expected.height().max(actual.height());
+	let mut pixels = 0;
+	let diff = RgbaImage::from_fn(width, height, |x, y| {
+		match (expected.get_pixel_checked(x, y), actual.get_pixel_checked(x, y)) {
+			(Some(e), Some(a)) if e == a => Rgba([e.0[0] / 4, e.0[1] / 4, e.0[2] / 4, 0xff]),
+			_ => {
+				pixels += 1;
+				Rgba([0xff, 0, 0, 0xff])
+			},
+		}
+	});
+	(pixels, diff)
+}
+
+#[test]
+fn test_diff_image() {
+	let expected = RgbaImage::from_pixel(2, 2, Rgba([0x80, 0x80, 0x80, 0xff]));
+	let mut actual = expected.clone();
+	actual.put_pixel(1, 0, Rgba([0, 0, 0, 0xff]));
+	let (pixels, diff) = diff_image(&expected, &actual);
+	assert_eq!(pixels, 1);
+	assert_eq!(diff.get_pixel(1, 0), &Rgba([0xff, 0, 0, 0xff]));
+	assert_eq!(diff.get_pixel(0, 0), &Rgba([0x20, 0x20, 0x20, 0xff]));
+
+	let (pixels, _) = diff_image(&expected, &RgbaImage::new(2, 3));
+	assert_eq!(pixels, 6);
+}
@@ -3,7 +3,15 @@ use embedded_graphics::{
 	prelude::{Dimensions, DrawTarget, RgbColor},
 	primitives::Rectangle,
 };
-use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer, Rgb, Rgba};
+use image::{DynamicImage, GenericImage, GenericImageView, ImageBuffer, Rgba};
+
+mod export;
+
+mod golden;
+pub use golden::{GoldenError, UPDATE_GOLDEN_ENV, diff_image};
+
+mod record;
+pub use record::GifRecorder;
 
 pub struct FrameOutput {
 	pub buffer: ImageBuffer<Rgba<u8>, Vec<u8>>,
@@ -38,23 +46,20 @@ impl DrawTarget for FrameOutput {
 			{
 				continue;
 			}
-			self.buffer.put_pixel(
-				pos.0.x as u32,
-				pos.0.y as u32,
-				Rgba([pos.1.r() << 3, pos.1.g() << 2, pos.1.b() << 3, 0xff]),
-			);
+			self.buffer.put_pixel(pos.0.x as u32, pos.0.y as u32, to_rgba(pos.1));
 		}
 		Ok(())
 	}
 
 	fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+		let area = area.intersection(&self.bounding_box());
 		let mut sub = self.buffer.sub_image(
 			area.top_left.x as _,
 			area.top_left.y as _,
 			area.size.width,
 			area.size.height,
 		);
-		let rgb = Rgba([color.r() << 3, color.g() << 2, color.b() << 3, 0xff]);
+		let rgb = to_rgba(color);
 		for y in 0..sub.height() {
 			for x in 0..sub.width() {
 				sub.put_pixel(x, y, rgb);
@@ -69,3 +74,11 @@ impl Dimensions for FrameOutput {
 		Rectangle::new((0, 0).into(), (self.buffer.width(), self.buffer.height()).into())
 	}
 }
+
+fn to_rgba(color: Rgb565) -> Rgba<u8> {
+	Rgba([color.r() << 3, color.g() << 2, color.b() << 3, 0xff])
+}
+
+fn from_rgba(pixel: &Rgba<u8>) -> Rgb565 {
+	Rgb565::new(pixel.0[0] >> 3, pixel.0[1] >> 2, pixel.0[2] >> 3)
+}
@@ -0,0 +1,56 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use image::{
+	Delay, Frame, ImageResult,
+	codecs::gif::{GifEncoder, Repeat},
+};
+
+use crate::FrameOutput;
+
+/// Collects rendered frames into an animated GIF.
+#[derive(Default)]
+pub struct GifRecorder {
+	frames: Vec<Frame>,
+}
+
+impl GifRecorder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add a snapshot of `disp`, shown for `delay_ms` milliseconds.
+	pub fn push(&mut self, disp: &FrameOutput, delay_ms: u32) {
+		self.frames.push(Frame::from_parts(
+			disp.buffer.clone(),
+			0,
+			0,
+			Delay::from_numer_denom_ms(delay_ms, 1),
+		));
+	}
+
+	/// Extend the display time of the previous frame (used for frames that did not change).
+	pub fn extend_last(&mut self, delay_ms: u32) {
+		if let Some(last) = self.frames.pop() {
+			let (numer, denom) = last.delay().numer_denom_ms();
+			let delay = Delay::from_numer_denom_ms(numer + delay_ms * denom, denom);
+			let (left, top) = (last.left(), last.top());
+			self.frames
+				.push(Frame::from_parts(last.into_buffer(), left, top, delay));
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.frames.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.frames.is_empty()
+	}
+
+	pub fn save(self, path: &Path) -> ImageResult<()> {
+		let file = BufWriter::new(File::create(path)?);
+		let mut encoder = GifEncoder::new(file);
+		encoder.set_repeat(Repeat::Infinite)?;
+		encoder.encode_frames(self.frames)
+	}
+}
@@ -1,74 +1,66 @@
-#![feature(round_char_boundary, hash_extract_if)]
+#![feature(hash_extract_if)]
 
 // build using
 // nix build .#packages.x86_64-linux-cross-muslpi.music && nix store sign -k ~/.local/share/nix-store-binary-cache-key-secret $(readlink -f result) && nix copy --to ssh://pi@himbeere-mit-musik $(readlink -f result) && echo $(readlink -f result)
 
-use std::{env, time::Duration};
+use std::{
+	env,
+	rc::Rc,
+	time::{Duration, SystemTime},
+};
 
 use command::{get_volume, list_folders, set_volume, start_mpv};
-use display_interface_spi::SPIInterface;
-use gpiocdev::{
-	Request,
-	line::{Bias, EdgeDetection},
-};
+use gpiocdev::Request;
 use mpv_status::MpvStatus;
 use playerctl_rust_wrapper::Playerctl;
-use raspi_lib::{BLACK, Draw, DrawTarget, Rng, TimeDisplay, new_rng};
-use rppal::{
-	gpio::Gpio,
-	hal::Delay,
-	spi::{Bus, Mode, SimpleHalSpiDevice, SlaveSelect, Spi},
+use raspi_lib::{
+	BLACK, BurnInPolicy, ButtonPin, DEFAULT_TIMEZONE, Display, Draw, DrawTarget, HardwareProfile, Locale, Oled, Rng,
+	SystemClock, TimeDisplay, Tz, new_rng,
 };
-use ssd1351::display::display::Ssd1351;
 use ui::Ui;
 
 mod command;
 mod mpv_status;
 mod ui;
 
-const BUTTON_PINS: &[u32] = &[17, 22, 5, 6, 26, 16];
+/// Wiring of the music player, can be replaced using `--hardware <file>`.
+fn default_hardware() -> HardwareProfile {
+	HardwareProfile {
+		buttons: vec![
+			ButtonPin::pull_down(17, 50),
+			ButtonPin::pull_down(22, 50),
+			ButtonPin::pull_up(5, 50),
+			ButtonPin::pull_up(6, 50),
+			ButtonPin::pull_up(26, 50),
+			ButtonPin::pull_up(16, 50),
+		],
+		..HardwareProfile::default()
+	}
+}
 
 fn main() {
 	let args: Vec<_> = env::args().map(|x| x.to_string()).collect();
 	let mut rng = new_rng();
+	let tz = match args.iter().position(|x| x == "--timezone") {
+		Some(idx) => raspi_lib::timezone(args.get(idx + 1).expect("missing time zone")).expect("unknown time zone"),
+		None => DEFAULT_TIMEZONE,
+	};
+	let locale = match args.iter().position(|x| x == "--locale") {
+		Some(idx) => Locale::from_name(args.get(idx + 1).expect("missing locale")).expect("unknown locale"),
+		None => Locale::default(),
+	};
+	let hardware = HardwareProfile::from_args(&args, default_hardware()).unwrap_or_else(|e| panic!("{e}"));
 
 	if rppal::system::DeviceInfo::new().is_ok() {
 		// Boot up pulseaudio socket
 		let _ = get_volume();
 
-		let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 19660800, Mode::Mode0).unwrap();
-		let gpio = Gpio::new().unwrap();
-		let dc = gpio.get(25).unwrap().into_output();
-		let mut rst = gpio.get(27).unwrap().into_output();
-
-		// Init SPI
-		let spii = SPIInterface::new(SimpleHalSpiDevice::new(spi), dc);
-		let mut disp = Ssd1351::new(spii);
-
-		// Reset & init display
-		disp.reset(&mut rst, &mut Delay).unwrap();
-		disp.turn_on().unwrap();
+		let mut disp = Display::open(&hardware.display).unwrap_or_else(|e| panic!("{e}"));
 		let _ = disp.clear(BLACK);
 
-		let mut lines = gpiocdev::Request::builder();
-		lines.on_chip("/dev/gpiochip0");
-		for &line in &BUTTON_PINS[0..2] {
-			lines
-				.with_line(line)
-				.with_edge_detection(EdgeDetection::RisingEdge)
-				.with_debounce_period(Duration::from_millis(50))
-				.with_bias(Bias::PullDown);
-		}
-		for &line in &BUTTON_PINS[2..6] {
-			lines
-				.with_line(line)
-				.with_edge_detection(EdgeDetection::FallingEdge)
-				.with_debounce_period(Duration::from_millis(50))
-				.with_bias(Bias::PullUp);
-		}
-		let lines = lines.request().unwrap();
+		let lines = hardware.request_buttons().unwrap();
 
-		real_main(disp, &mut rng, lines);
+		real_main(disp, &mut rng, lines, &hardware, tz, locale);
 	} else {
 		pc_main();
 	}
@@ -180,15 +172,36 @@ fn pc_main() {
 	});
 }
 
-fn real_main(mut disp: Ssd1351<SPIInterface<SimpleHalSpiDevice, rppal::gpio::OutputPin>>, rng: &mut Rng, lines: Request) {
+fn real_main(
+	mut disp: Display<Oled>,
+	rng: &mut Rng,
+	lines: Request,
+	hardware: &HardwareProfile,
+	tz: &'static Tz,
+	locale: Locale,
+) {
 	let mut mpv = MpvStatus::new();
-	let mut time = TimeDisplay::new();
+	let mut time = TimeDisplay::new(tz, Rc::new(SystemClock), locale);
 	let mut active_ui: Option<Ui> = None;
+	let burn_in = BurnInPolicy::default();
 	loop {
+		// keep the cover art and track names from burning in
+		let minutes = SystemTime::now()
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.map_or(0, |x| x.as_secs() as i64 / 60);
+		let moved = disp.set_shift(burn_in.offset(minutes)) | disp.set_inverted(burn_in.inverted(minutes));
+		if moved {
+			let _ = disp.clear(BLACK);
+			mpv.redraw();
+			time.redraw();
+			if let Some(ui) = &mut active_ui {
+				Draw::<Display<Oled>>::redraw(ui);
+			}
+		}
 		// check user input
 		while lines.has_edge_event() == Ok(true) {
 			let ev = lines.read_edge_event().unwrap();
-			let idx = BUTTON_PINS.iter().position(|&offset| offset == ev.offset).unwrap();
+			let idx = hardware.button_index(ev.offset).unwrap();
 			if let Some(mut ai) = active_ui {
 				let res = ai.handle(idx);
 				match res {
@@ -248,10 +261,10 @@ fn real_main(mut disp: Ssd1351<SPIInterface<SimpleHalSpiDevice, rppal::gpio::Out
 				}
 			}
 		}
-		let mut buffer_dirty = false;
+		let mut buffer_dirty = moved;
 		if let Some(d) = &active_ui {
 			buffer_dirty |= d.draw(&mut disp, rng).unwrap();
-			if d.should_close() {
+			if Draw::<Display<Oled>>::expired(d) {
 				active_ui = None;
 				time.redraw();
 			}
@@ -7,7 +7,9 @@ use std::{
 use base64::{Engine, prelude::BASE64_STANDARD};
 use image::{DynamicImage, GenericImageView, ImageFormat, ImageReader};
 use playerctl_rust_wrapper::{PlayerMetadata, Playerctl};
-use raspi_lib::{BLACK, Draw, DrawTarget, Drawable, FONT, Pixel, Point, Rectangle, Rgb565, Screensaver, Text};
+use raspi_lib::{
+	BLACK, Draw, DrawTarget, Drawable, FONT, Pixel, Point, Rectangle, Rgb565, Screensaver, Text, draw_marquee,
+};
 
 #[derive(Clone)]
 pub struct MpvStatus {
@@ -37,6 +39,11 @@ impl MpvStatus {
 	pub fn active(&self) -> bool {
 		self.metadata.lock().unwrap().contains_key("mpv")
 	}
+
+	/// Draw the cover art again on the next frame.
+	pub fn redraw(&mut self) {
+		*self.prev_art.lock().unwrap() = None;
+	}
 }
 
 impl<D: DrawTarget<Color = Rgb565>> Screensaver<D> for MpvStatus {
@@ -136,49 +143,29 @@ impl<D: DrawTarget<Color = Rgb565>> Draw<D> for MpvStatus {
 					buffer_dirty = true;
 				}
 			}
-			macro_rules! draw_scrolling {
-				($string:expr, $y:expr) => {
-					let max_len = 128 / 10;
-					if $string.len() <= max_len {
-						let size = $string.len() * 10;
-						let pad = (max_len * 10 - size) / 2;
-						Text::new($string, Point::new(4 + pad as i32, $y), FONT).draw(disp)?;
-						#[allow(unused_assignments)]
-						{
-							buffer_dirty |= metadata_changed;
-						}
-					} else {
-						let pad = " ".repeat(max_len);
-						let full = format!("{pad}{}{pad}  ", $string);
-						let idx_start = iters as usize % (full.len() - max_len);
-						Text::new(
-							&full[full.ceil_char_boundary(idx_start)..full.floor_char_boundary(idx_start + max_len)],
-							Point::new(4, $y),
-							FONT,
-						)
-						.draw(disp)?;
-						#[allow(unused_assignments)]
-						{
-							buffer_dirty = true;
-						}
-					}
-				};
-			}
+			// centered if short enough, scrolling otherwise
+			let draw_scrolling = |disp: &mut D, text: &str, y: i32| {
+				let max_len: usize = 128 / 10;
+				let pad = max_len.saturating_sub(text.chars().count()) * 10 / 2;
+				draw_marquee(disp, text, Point::new(4 + pad as i32, y), max_len, iters as usize, FONT)
+			};
 			disp.fill_solid(&Rectangle::new((4, 20 - 15).into(), (128 - 4, 20).into()), BLACK)?;
 			disp.fill_solid(&Rectangle::new((4, 116 - 15).into(), (128 - 4, 20).into()), BLACK)?;
+			let mut scrolling = false;
 			if let Some(artist) = &d.xesam_artist {
-				draw_scrolling!(artist, 20);
+				scrolling |= draw_scrolling(disp, artist, 20)?;
 				if let Some(title) = &d.xesam_title {
-					draw_scrolling!(title, 116);
+					scrolling |= draw_scrolling(disp, title, 116)?;
 				}
 			} else if let Some(title) = &d.xesam_title {
 				if let Some((artist, song)) = title.split_once(" - ") {
-					draw_scrolling!(artist, 20);
-					draw_scrolling!(song, 116);
+					scrolling |= draw_scrolling(disp, artist, 20)?;
+					scrolling |= draw_scrolling(disp, song, 116)?;
 				} else {
-					draw_scrolling!(title, 116);
+					scrolling |= draw_scrolling(disp, title, 116)?;
 				}
 			}
+			buffer_dirty |= scrolling || metadata_changed;
 			if let Some(prog) = positions.get("mpv") {
 				disp.fill_solid(&Rectangle::new((68, 64 - 20 - 15).into(), (128 - 68, 20).into()), BLACK)?;
 				let secs = prog / 1_000_000;
@@ -196,6 +183,10 @@ impl<D: DrawTarget<Color = Rgb565>> Draw<D> for MpvStatus {
 		Ok(buffer_dirty)
 	}
 
+	fn redraw(&mut self) {
+		MpvStatus::redraw(self);
+	}
+
 	fn as_any(&self) -> &dyn std::any::Any {
 		self
 	}
@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 
-use raspi_lib::{BLACK, Draw, DrawTarget, Drawable, FONT, FONT_RED, Point, Rgb565, Text};
+use raspi_lib::{BLACK, Draw, DrawTarget, FONT, FONT_10X20, FONT_RED, Point, Rgb565, TextNotification, draw_marquee};
 
 pub enum UiResult {
 	Ignore,
@@ -15,40 +15,40 @@ use crate::command::{execute, set_volume};
 pub struct Ui {
 	id: &'static str,
 	drawn: RefCell<u32>,
-	aux1: i32,
 	aux2: Vec<String>,
 	aux3: usize,
+	/// Screens consisting only of text, all but `select`.
+	text: Option<TextNotification>,
 }
 
 impl Ui {
 	pub fn new(id: &'static str) -> Self {
-		Ui {
-			id,
-			drawn: RefCell::new(0),
-			aux1: 0,
-			aux2: vec![],
-			aux3: 0,
-		}
+		Ui::new_aux1(id, 0)
 	}
 
 	pub fn new_aux1(id: &'static str, aux1: i32) -> Self {
+		let text = match id {
+			"exit" => Some(TextNotification::new("Confirm shutdown?")),
+			"exit_confirmed" => Some(TextNotification::new("Unplug in 30 seconds")),
+			// closes after a few frames
+			"volume" => Some(
+				TextNotification::new(format!("{aux1}%"))
+					.with_title("Volume")
+					.with_timeout(2),
+			),
+			_ => None,
+		};
 		Ui {
 			id,
 			drawn: RefCell::new(0),
-			aux1,
 			aux2: vec![],
 			aux3: 0,
+			text: text.map(|x| x.with_font(&FONT_10X20)),
 		}
 	}
 
 	pub fn new_aux2(id: &'static str, aux2: Vec<String>) -> Self {
-		Ui {
-			id,
-			drawn: RefCell::new(0),
-			aux1: 0,
-			aux2,
-			aux3: 0,
-		}
+		Ui { aux2, ..Ui::new(id) }
 	}
 
 	pub fn handle(&mut self, button: usize) -> UiResult {
@@ -89,80 +89,47 @@ impl Ui {
 			_ => Close,
 		}
 	}
-
-	pub fn should_close(&self) -> bool {
-		matches!(self.id, "volume") && *self.drawn.borrow() > 2
-	}
 }
 
 impl<D: DrawTarget<Color = Rgb565>> Draw<D> for Ui {
-	fn draw(&self, disp: &mut D, _rng: &mut raspi_lib::Rng) -> Result<bool, <D as DrawTarget>::Error> {
-		*self.drawn.borrow_mut() += 1;
-		if *self.drawn.borrow() > 1 && self.id != "select" {
-			return Ok(false);
+	fn draw(&self, disp: &mut D, rng: &mut raspi_lib::Rng) -> Result<bool, <D as DrawTarget>::Error> {
+		if let Some(text) = &self.text {
+			return text.draw(disp, rng);
 		}
+		*self.drawn.borrow_mut() += 1;
 		let iters = *self.drawn.borrow() - 1;
 		disp.clear(BLACK)?;
 
-		macro_rules! draw_scrolling {
-			($string:expr, $y:expr, $styl:expr) => {
-				let max_len = 128 / 10;
-				if $string.len() <= max_len {
-					let size = $string.len() * 10;
-					let pad = (max_len * 10 - size) / 2;
-					Text::new($string, Point::new(4 + pad as i32, $y), $styl).draw(disp)?;
-				} else {
-					let pad = " ".repeat(max_len);
-					let full = format!("   {}{pad}  ", $string);
-					let mut idx_start = iters as usize % (full.len() - max_len);
-					if idx_start < 3 {
-						idx_start = 3;
-					}
-					Text::new(
-						&full[full.ceil_char_boundary(idx_start)..full.floor_char_boundary(idx_start + max_len)],
-						Point::new(4, $y),
-						$styl,
-					)
-					.draw(disp)?;
-				}
-			};
+		// the folder list, all other screens are text
+		let mut pages = self.aux2.chunks(6);
+		let active_page = self.aux3 / 6;
+		let active_idx = self.aux3 % 6;
+		let page = pages.nth(active_page).unwrap();
+		for i in 0..6 {
+			let styl = if i == active_idx { FONT_RED } else { FONT };
+			if i < page.len() {
+				draw_marquee(
+					disp,
+					&page[i],
+					Point::new(4, 14 + i as i32 * 20),
+					12,
+					iters as usize,
+					styl,
+				)?;
+			}
 		}
+		Ok(true)
+	}
 
-		match self.id {
-			"exit" => {
-				Text::new("Confirm\n   shutdown?", Point::new(4, 64 - 20), FONT).draw(disp)?;
-			},
-			"exit_confirmed" => {
-				Text::new("Unplug in\n  30 seconds", Point::new(4, 64 - 20), FONT).draw(disp)?;
-			},
-			"volume" => {
-				Text::new(
-					&format!("Volume\n{: >12}", format!("{}%", self.aux1)),
-					Point::new(4, 64 - 20),
-					FONT,
-				)
-				.draw(disp)?;
-			},
-			"select" => {
-				let mut pages = self.aux2.chunks(6);
-				let active_page = self.aux3 / 6;
-				let active_idx = self.aux3 % 6;
-				let page = pages.nth(active_page).unwrap();
-				for i in 0..6 {
-					let styl = if i == active_idx { FONT_RED } else { FONT };
-					if i < page.len() {
-						let name = &page[i];
-						if name.len() <= 12 {
-							Text::new(name, Point::new(4, 14 + i as i32 * 20), styl).draw(disp)?;
-						} else {
-							draw_scrolling!(name, 14 + i as i32 * 20, styl);
-						}
-					}
-				}
-			},
-			_ => {},
+	fn expired(&self) -> bool {
+		self.text.as_ref().is_some_and(|x| Draw::<D>::expired(x))
+	}
+
+	fn redraw(&mut self) {
+		if let Some(text) = &mut self.text {
+			Draw::<D>::redraw(text);
 		}
-		Ok(true)
+		*self.drawn.get_mut() = 0;
 	}
 
 	fn as_any(&self) -> &dyn std::any::Any {
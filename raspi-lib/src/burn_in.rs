@@ -0,0 +1,109 @@
+//! OLED burn-in protection.
+
+use embedded_graphics::prelude::Point;
+use serde::Deserialize;
+use time::Duration;
+
+/// How a screen is kept from burning in.
+///
+/// Apply it using [`Display::set_shift`](crate::Display::set_shift),
+/// [`Display::set_inverted`](crate::Display::set_inverted) and [`Display::set_blank`](crate::Display::set_blank).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BurnInPolicy {
+	/// Largest distance in pixels the screen is moved in each direction, 0 disables shifting.
+	pub shift: u8,
+	/// Minutes between two moves.
+	pub shift_minutes: u32,
+	/// Minutes per hour the colors are inverted, 0 disables inversion.
+	pub invert_minutes: u32,
+	/// Minutes without input or a new screen after which the display is blanked, 0 disables blanking.
+	pub idle_blank_minutes: u32,
+}
+
+impl Default for BurnInPolicy {
+	fn default() -> Self {
+		BurnInPolicy {
+			shift: 2,
+			shift_minutes: 5,
+			invert_minutes: 0,
+			idle_blank_minutes: 0,
+		}
+	}
+}
+
+impl BurnInPolicy {
+	/// No protection, for screens that take care of it themselves.
+	pub const OFF: BurnInPolicy = BurnInPolicy {
+		shift: 0,
+		shift_minutes: 0,
+		invert_minutes: 0,
+		idle_blank_minutes: 0,
+	};
+
+	/// Offset of the screen `minutes` after the Unix epoch.
+	///
+	/// Walks through all offsets row by row, reversing every other row, and then back the same way,
+	/// so the screen only moves by a single pixel at a time.
+	pub fn offset(&self, minutes: i64) -> Point {
+		if self.shift == 0 || self.shift_minutes == 0 {
+			return Point::zero();
+		}
+		let shift = self.shift as i64;
+		let side = 2 * shift + 1;
+		let last = side * side - 1;
+		let mut step = (minutes / self.shift_minutes as i64).rem_euclid(2 * last);
+		if step > last {
+			step = 2 * last - step;
+		}
+		let (row, mut column) = (step / side, step % side);
+		if row % 2 == 1 {
+			column = side - 1 - column;
+		}
+		Point::new((column - shift) as i32, (row - shift) as i32)
+	}
+
+	/// Whether the colors are inverted `minutes` after the Unix epoch.
+	pub fn inverted(&self, minutes: i64) -> bool {
+		minutes.rem_euclid(60) < self.invert_minutes as i64
+	}
+
+	pub fn idle_blank(&self) -> Option<Duration> {
+		(self.idle_blank_minutes > 0).then(|| Duration::minutes(self.idle_blank_minutes as i64))
+	}
+}
+
+#[test]
+fn test_offset() {
+	let policy = BurnInPolicy {
+		shift: 1,
+		shift_minutes: 5,
+		..BurnInPolicy::OFF
+	};
+	let offsets: Vec<_> = (0..11).map(|x| policy.offset(x * 5)).map(|p| (p.x, p.y)).collect();
+	assert_eq!(
+		offsets,
+		[
+			(-1, -1),
+			(0, -1),
+			(1, -1),
+			(1, 0),
+			(0, 0),
+			(-1, 0),
+			(-1, 1),
+			(0, 1),
+			(1, 1),
+			(0, 1),
+			(-1, 1)
+		]
+	);
+	// back at the start after walking back, moving one pixel at a time
+	assert_eq!(policy.offset(16 * 5), Point::new(-1, -1));
+	let policy = BurnInPolicy { shift: 2, ..policy };
+	for step in 0..100 {
+		let (a, b) = (policy.offset(step * 5), policy.offset(step * 5 + 5));
+		assert!((a.x - b.x).abs() + (a.y - b.y).abs() == 1, "{a:?} {b:?}");
+	}
+	assert_eq!(policy.offset(4), policy.offset(0));
+	assert_eq!(BurnInPolicy::OFF.offset(12345), Point::zero());
+}
@@ -0,0 +1,44 @@
+use std::cell::Cell;
+
+use time::{Duration, OffsetDateTime};
+
+/// Source of the current time.
+pub trait Clock {
+	fn now_utc(&self) -> OffsetDateTime;
+}
+
+/// The real system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now_utc(&self) -> OffsetDateTime {
+		OffsetDateTime::now_utc()
+	}
+}
+
+/// Manually advanced clock for rendering tests.
+#[derive(Debug, Clone)]
+pub struct FakeClock {
+	now: Cell<OffsetDateTime>,
+}
+
+impl FakeClock {
+	pub fn new(start: OffsetDateTime) -> Self {
+		FakeClock { now: Cell::new(start) }
+	}
+
+	pub fn set(&self, now: OffsetDateTime) {
+		self.now.set(now);
+	}
+
+	pub fn advance(&self, duration: Duration) {
+		self.now.set(self.now.get() + duration);
+	}
+}
+
+impl Clock for FakeClock {
+	fn now_utc(&self) -> OffsetDateTime {
+		self.now.get()
+	}
+}
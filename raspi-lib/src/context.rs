@@ -9,6 +9,16 @@ pub trait Draw<D: DrawTarget<Color = Rgb565>> {
 	fn expired(&self) -> bool {
 		false
 	}
+	/// Draw everything again on the next frame, e.g. after the display was cleared.
+	fn redraw(&mut self) {}
+	/// Draws with a higher priority stay on top of draws with a lower priority.
+	fn priority(&self) -> u8 {
+		0
+	}
+	/// Short name used in status reports.
+	fn name(&self) -> &'static str {
+		std::any::type_name::<Self>()
+	}
 	fn as_any(&self) -> &dyn Any;
 	fn as_any_mut(&mut self) -> &mut dyn Any;
 }
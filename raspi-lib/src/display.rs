@@ -0,0 +1,368 @@
+//! Bring-up of the SSD1351 OLED and a common interface for on- and off-device targets.
+
+use std::{error, fmt};
+
+use display_interface::DisplayError as InterfaceError;
+use display_interface_spi::SPIInterface;
+use embedded_graphics::Pixel;
+use embedded_graphics::pixelcolor::{Rgb565, RgbColor};
+use embedded_graphics::prelude::{Dimensions, DrawTarget, Point};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::transform::Transform;
+use frame_output::FrameOutput;
+use rppal::gpio::{Gpio, OutputPin};
+use rppal::hal::Delay;
+use rppal::spi::{Bus, Mode, SimpleHalSpiDevice, SlaveSelect, Spi};
+use ssd1351::display::display::Ssd1351;
+
+use crate::hardware::DisplayPins;
+
+/// The OLED connected via SPI.
+pub type Oled = Ssd1351<SPIInterface<SimpleHalSpiDevice, OutputPin>>;
+
+#[derive(Debug)]
+pub enum DisplayError {
+	/// Opening the SPI device failed, e.g. `/dev/spidev0.0` is missing.
+	Spi(rppal::spi::Error),
+	Gpio(rppal::gpio::Error),
+	/// Bus or slave select not supported by the SoC.
+	InvalidSpi {
+		bus: u8,
+		slave_select: u8,
+	},
+	Reset,
+	/// Writing to the display failed.
+	Interface(InterfaceError),
+}
+
+impl fmt::Display for DisplayError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			DisplayError::Spi(e) => write!(f, "failed to open SPI device: {e}"),
+			DisplayError::Gpio(e) => write!(f, "failed to set up display GPIO pins: {e}"),
+			DisplayError::InvalidSpi { bus, slave_select } => {
+				write!(f, "invalid SPI device: bus {bus}, slave select {slave_select}")
+			},
+			DisplayError::Reset => write!(f, "failed to reset the display"),
+			DisplayError::Interface(e) => write!(f, "display interface error: {e:?}"),
+		}
+	}
+}
+
+impl error::Error for DisplayError {}
+
+impl From<rppal::spi::Error> for DisplayError {
+	fn from(e: rppal::spi::Error) -> Self {
+		DisplayError::Spi(e)
+	}
+}
+
+impl From<rppal::gpio::Error> for DisplayError {
+	fn from(e: rppal::gpio::Error) -> Self {
+		DisplayError::Gpio(e)
+	}
+}
+
+impl From<InterfaceError> for DisplayError {
+	fn from(e: InterfaceError) -> Self {
+		DisplayError::Interface(e)
+	}
+}
+
+/// Hardware behind a [`Display`].
+pub trait Panel: DrawTarget<Color = Rgb565> {
+	/// Transfer the drawn frame, if the panel is buffered.
+	fn flush(&mut self) -> Result<(), DisplayError>;
+
+	/// Turn the panel off (`true`) or on again (`false`), keeping its contents.
+	fn set_sleep(&mut self, sleep: bool) -> Result<(), DisplayError>;
+
+	/// Set the brightness (0 to 255) in hardware.
+	/// Returns `false` if the panel has no such control, the colors are scaled instead.
+	fn set_contrast(&mut self, _brightness: u8) -> Result<bool, DisplayError> {
+		Ok(false)
+	}
+}
+
+impl Panel for Oled {
+	fn flush(&mut self) -> Result<(), DisplayError> {
+		Ok(Ssd1351::flush(self)?)
+	}
+
+	fn set_sleep(&mut self, sleep: bool) -> Result<(), DisplayError> {
+		if sleep {
+			self.turn_off()?;
+		} else {
+			self.turn_on()?;
+		}
+		Ok(())
+	}
+
+	/// Uses the master contrast current, which has 16 steps.
+	fn set_contrast(&mut self, brightness: u8) -> Result<bool, DisplayError> {
+		Ssd1351::set_contrast(self, brightness >> 4)?;
+		Ok(true)
+	}
+}
+
+/// Off-device target, e.g. for headless rendering or a desktop window.
+/// Sleeping clears the frame, brightness is applied by scaling the colors.
+impl Panel for FrameOutput {
+	fn flush(&mut self) -> Result<(), DisplayError> {
+		Ok(())
+	}
+
+	fn set_sleep(&mut self, sleep: bool) -> Result<(), DisplayError> {
+		if sleep {
+			let _ = self.clear(Rgb565::BLACK);
+		}
+		Ok(())
+	}
+}
+
+/// A [`Panel`] with brightness control, sleep and burn-in protection.
+///
+/// Panels without a hardware brightness control get their colors scaled instead.
+/// Nothing is drawn while the display sleeps or is blanked.
+pub struct Display<P> {
+	panel: P,
+	/// 0 (black) to 255 (full brightness).
+	brightness: u8,
+	/// Factor applied to every drawn color, 255 if the panel dims itself.
+	scale: u8,
+	asleep: bool,
+	blanked: bool,
+	/// Offset added to every drawn pixel.
+	shift: Point,
+	inverted: bool,
+}
+
+impl<P: Panel> Display<P> {
+	pub fn new(panel: P) -> Self {
+		Display {
+			panel,
+			brightness: u8::MAX,
+			scale: u8::MAX,
+			asleep: false,
+			blanked: false,
+			shift: Point::zero(),
+			inverted: false,
+		}
+	}
+
+	pub fn panel(&self) -> &P {
+		&self.panel
+	}
+
+	pub fn panel_mut(&mut self) -> &mut P {
+		&mut self.panel
+	}
+
+	pub fn flush(&mut self) -> Result<(), DisplayError> {
+		self.panel.flush()
+	}
+
+	pub fn brightness(&self) -> u8 {
+		self.brightness
+	}
+
+	/// Returns whether the frame has to be drawn again to show the new brightness,
+	/// which is the case if the colors are scaled in software.
+	pub fn set_brightness(&mut self, brightness: u8) -> Result<bool, DisplayError> {
+		if brightness == self.brightness {
+			return Ok(false);
+		}
+		self.brightness = brightness;
+		if self.panel.set_contrast(brightness)? {
+			return Ok(false);
+		}
+		self.scale = brightness;
+		Ok(true)
+	}
+
+	pub fn is_asleep(&self) -> bool {
+		self.asleep
+	}
+
+	/// Returns whether the frame has to be drawn again, which is the case after waking up.
+	pub fn set_sleep(&mut self, sleep: bool) -> Result<bool, DisplayError> {
+		let was_off = self.is_off();
+		self.asleep = sleep;
+		self.power(was_off)
+	}
+
+	pub fn is_blanked(&self) -> bool {
+		self.blanked
+	}
+
+	/// Like [`Display::set_sleep`], kept apart so burn-in protection and sleep do not interfere.
+	pub fn set_blank(&mut self, blank: bool) -> Result<bool, DisplayError> {
+		let was_off = self.is_off();
+		self.blanked = blank;
+		self.power(was_off)
+	}
+
+	fn is_off(&self) -> bool {
+		self.asleep || self.blanked
+	}
+
+	fn power(&mut self, was_off: bool) -> Result<bool, DisplayError> {
+		let off = self.is_off();
+		if off == was_off {
+			return Ok(false);
+		}
+		self.panel.set_sleep(off)?;
+		Ok(!off)
+	}
+
+	pub fn shift(&self) -> Point {
+		self.shift
+	}
+
+	/// Move everything drawn afterwards by `shift`.
+	/// Returns whether the frame has to be drawn again.
+	pub fn set_shift(&mut self, shift: Point) -> bool {
+		let changed = shift != self.shift;
+		self.shift = shift;
+		changed
+	}
+
+	/// Invert the colors of everything drawn afterwards.
+	/// Returns whether the frame has to be drawn again.
+	pub fn set_inverted(&mut self, inverted: bool) -> bool {
+		let changed = inverted != self.inverted;
+		self.inverted = inverted;
+		changed
+	}
+
+	fn color(&self, color: Rgb565) -> Rgb565 {
+		transform_color(color, self.inverted, self.scale)
+	}
+
+	fn is_plain(&self) -> bool {
+		self.scale == u8::MAX && self.shift == Point::zero() && !self.inverted
+	}
+}
+
+fn transform_color(color: Rgb565, inverted: bool, scale: u8) -> Rgb565 {
+	let color = if inverted {
+		Rgb565::new(
+			Rgb565::MAX_R - color.r(),
+			Rgb565::MAX_G - color.g(),
+			Rgb565::MAX_B - color.b(),
+		)
+	} else {
+		color
+	};
+	if scale == u8::MAX {
+		return color;
+	}
+	let dim = |x: u8| (x as u16 * scale as u16 / u8::MAX as u16) as u8;
+	Rgb565::new(dim(color.r()), dim(color.g()), dim(color.b()))
+}
+
+impl Display<Oled> {
+	/// Open the SPI device, reset the OLED and turn it on.
+	pub fn open(pins: &DisplayPins) -> Result<Self, DisplayError> {
+		let invalid = || DisplayError::InvalidSpi {
+			bus: pins.spi_bus,
+			slave_select: pins.slave_select,
+		};
+		let bus = match pins.spi_bus {
+			0 => Bus::Spi0,
+			1 => Bus::Spi1,
+			2 => Bus::Spi2,
+			3 => Bus::Spi3,
+			4 => Bus::Spi4,
+			5 => Bus::Spi5,
+			6 => Bus::Spi6,
+			_ => return Err(invalid()),
+		};
+		let slave_select = match pins.slave_select {
+			0 => SlaveSelect::Ss0,
+			1 => SlaveSelect::Ss1,
+			2 => SlaveSelect::Ss2,
+			_ => return Err(invalid()),
+		};
+		let spi = Spi::new(bus, slave_select, pins.clock_hz, Mode::Mode0)?;
+		let gpio = Gpio::new()?;
+		let dc = gpio.get(pins.dc)?.into_output();
+		let mut rst = gpio.get(pins.rst)?.into_output();
+
+		let interface = SPIInterface::new(SimpleHalSpiDevice::new(spi), dc);
+		let mut disp = Ssd1351::new(interface);
+		disp.reset(&mut rst, &mut Delay).map_err(|_| DisplayError::Reset)?;
+		disp.turn_on()?;
+		Ok(Display::new(disp))
+	}
+}
+
+impl<P: Panel> DrawTarget for Display<P> {
+	type Color = Rgb565;
+	type Error = P::Error;
+
+	fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+	where
+		I: IntoIterator<Item = Pixel<Rgb565>>,
+	{
+		if self.is_off() {
+			return Ok(());
+		}
+		if self.is_plain() {
+			return self.panel.draw_iter(pixels);
+		}
+		let area = self.panel.bounding_box();
+		let (shift, inverted, scale) = (self.shift, self.inverted, self.scale);
+		self.panel.draw_iter(
+			pixels
+				.into_iter()
+				.map(|Pixel(p, c)| Pixel(p + shift, transform_color(c, inverted, scale)))
+				.filter(|x| area.contains(x.0)),
+		)
+	}
+
+	fn fill_solid(&mut self, area: &Rectangle, color: Rgb565) -> Result<(), Self::Error> {
+		if self.is_off() {
+			return Ok(());
+		}
+		let area = area.translate(self.shift).intersection(&self.panel.bounding_box());
+		self.panel.fill_solid(&area, self.color(color))
+	}
+
+	fn clear(&mut self, color: Rgb565) -> Result<(), Self::Error> {
+		if self.is_off() {
+			return Ok(());
+		}
+		self.panel.clear(self.color(color))
+	}
+}
+
+impl<P: Panel> Dimensions for Display<P> {
+	fn bounding_box(&self) -> Rectangle {
+		self.panel.bounding_box()
+	}
+}
+
+#[test]
+fn test_brightness() {
+	use embedded_graphics::Drawable;
+
+	let mut disp = Display::new(FrameOutput::new(2, 1));
+	assert!(disp.set_brightness(128).unwrap());
+	Pixel(Point::new(0, 0), Rgb565::WHITE).draw(&mut disp).unwrap();
+	let pixel = disp.panel().buffer.get_pixel(0, 0);
+	assert!((115..=135).contains(&pixel[0]), "{pixel:?}");
+	assert!(!disp.set_sleep(true).unwrap());
+	assert_eq!(disp.panel().buffer.get_pixel(0, 0)[0], 0);
+	// dropped while asleep
+	Pixel(Point::new(0, 0), Rgb565::WHITE).draw(&mut disp).unwrap();
+	assert_eq!(disp.panel().buffer.get_pixel(0, 0)[0], 0);
+	assert!(disp.set_sleep(false).unwrap());
+
+	assert!(disp.set_shift(Point::new(1, 0)));
+	assert!(disp.set_inverted(true));
+	disp.clear(Rgb565::BLACK).unwrap();
+	Pixel(Point::new(0, 0), Rgb565::WHITE).draw(&mut disp).unwrap();
+	assert!((115..=135).contains(&disp.panel().buffer.get_pixel(0, 0)[0]));
+	assert_eq!(disp.panel().buffer.get_pixel(1, 0)[0], 0);
+}
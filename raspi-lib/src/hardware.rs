@@ -0,0 +1,215 @@
+//! GPIO wiring of a build.
+//!
+//! Every binary takes its pins from a [`HardwareProfile`] instead of hardcoding line offsets.
+//! The defaults describe the original `raspi-oled` build.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fmt, fs, io};
+
+use gpiocdev::Request;
+use gpiocdev::line::{Bias, EdgeDetection};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HardwareProfile {
+	/// GPIO character device.
+	pub chip: PathBuf,
+	pub display: DisplayPins,
+	/// Line driving the buzzer.
+	pub buzzer: u32,
+	/// Data line of the AM2302 sensor.
+	pub am2302: u32,
+	/// Buttons in the order expected by the binary (e.g. up, down, select for `main_loop`).
+	pub buttons: Vec<ButtonPin>,
+}
+
+/// SPI device and BCM pin numbers of the SSD1351 control lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayPins {
+	/// Data/command select.
+	pub dc: u8,
+	pub rst: u8,
+	/// SPI bus and chip select, `/dev/spidev<bus>.<slave_select>`.
+	pub spi_bus: u8,
+	pub slave_select: u8,
+	pub clock_hz: u32,
+}
+
+impl Default for DisplayPins {
+	fn default() -> Self {
+		DisplayPins {
+			dc: 25,
+			rst: 27,
+			spi_bus: 0,
+			slave_select: 0,
+			clock_hz: 19_660_800,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonPin {
+	pub line: u32,
+	#[serde(default = "default_bias")]
+	pub bias: PinBias,
+	/// Edge signalling a press.
+	#[serde(default = "default_edge")]
+	pub edge: PinEdge,
+	#[serde(default = "default_debounce_ms")]
+	pub debounce_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinBias {
+	PullUp,
+	PullDown,
+	Disabled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinEdge {
+	Rising,
+	Falling,
+	Both,
+}
+
+fn default_bias() -> PinBias {
+	PinBias::PullDown
+}
+
+fn default_edge() -> PinEdge {
+	PinEdge::Rising
+}
+
+fn default_debounce_ms() -> u64 {
+	5
+}
+
+impl ButtonPin {
+	/// Button connecting the line to 3.3 V, the default wiring.
+	pub const fn pull_down(line: u32, debounce_ms: u64) -> Self {
+		ButtonPin {
+			line,
+			bias: PinBias::PullDown,
+			edge: PinEdge::Rising,
+			debounce_ms,
+		}
+	}
+
+	/// Button connecting the line to ground.
+	pub const fn pull_up(line: u32, debounce_ms: u64) -> Self {
+		ButtonPin {
+			line,
+			bias: PinBias::PullUp,
+			edge: PinEdge::Falling,
+			debounce_ms,
+		}
+	}
+}
+
+impl Default for HardwareProfile {
+	fn default() -> Self {
+		HardwareProfile {
+			chip: "/dev/gpiochip0".into(),
+			display: DisplayPins::default(),
+			buzzer: 12,
+			am2302: 26,
+			buttons: vec![
+				ButtonPin::pull_down(5, 5),
+				ButtonPin::pull_down(6, 5),
+				ButtonPin::pull_down(19, 5),
+			],
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum HardwareError {
+	Io(PathBuf, io::Error),
+	Parse(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for HardwareError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			HardwareError::Io(path, e) => write!(f, "failed to read {}: {e}", path.display()),
+			HardwareError::Parse(path, e) => write!(f, "failed to parse {}: {e}", path.display()),
+		}
+	}
+}
+
+impl std::error::Error for HardwareError {}
+
+impl HardwareProfile {
+	/// Load a profile from a TOML file, missing keys take the default values.
+	pub fn load(path: &Path) -> Result<Self, HardwareError> {
+		let text = fs::read_to_string(path).map_err(|e| HardwareError::Io(path.to_owned(), e))?;
+		toml::from_str(&text).map_err(|e| HardwareError::Parse(path.to_owned(), e))
+	}
+
+	/// Load the profile given by `--hardware <file>`, or return `default`.
+	pub fn from_args(args: &[String], default: Self) -> Result<Self, HardwareError> {
+		match args
+			.iter()
+			.position(|x| x == "--hardware")
+			.and_then(|idx| args.get(idx + 1))
+		{
+			Some(path) => Self::load(Path::new(path)),
+			None => Ok(default),
+		}
+	}
+
+	/// Request all button lines with edge detection.
+	pub fn request_buttons(&self) -> gpiocdev::Result<Request> {
+		let mut builder = Request::builder();
+		builder.on_chip(&self.chip);
+		for button in &self.buttons {
+			builder
+				.with_line(button.line)
+				.with_edge_detection(match button.edge {
+					PinEdge::Rising => EdgeDetection::RisingEdge,
+					PinEdge::Falling => EdgeDetection::FallingEdge,
+					PinEdge::Both => EdgeDetection::BothEdges,
+				})
+				.with_debounce_period(Duration::from_millis(button.debounce_ms))
+				.with_bias(match button.bias {
+					PinBias::PullUp => Bias::PullUp,
+					PinBias::PullDown => Bias::PullDown,
+					PinBias::Disabled => Bias::Disabled,
+				});
+		}
+		builder.request()
+	}
+
+	/// Index of the button on line `offset`.
+	pub fn button_index(&self, offset: u32) -> Option<usize> {
+		self.buttons.iter().position(|x| x.line == offset)
+	}
+}
+
+#[test]
+fn test_hardware_profile() {
+	let profile: HardwareProfile = toml::from_str(
+		r#"
+		buzzer = 13
+		display = { dc = 24, rst = 23 }
+		buttons = [{ line = 17 }, { line = 5, bias = "pull_up", edge = "falling", debounce_ms = 50 }]
+		"#,
+	)
+	.unwrap();
+	assert_eq!(profile.chip, HardwareProfile::default().chip);
+	assert_eq!((profile.display.dc, profile.display.rst), (24, 23));
+	assert_eq!(profile.display.clock_hz, DisplayPins::default().clock_hz);
+	assert_eq!(
+		profile.buttons,
+		[ButtonPin::pull_down(17, 5), ButtonPin::pull_up(5, 50)]
+	);
+	assert_eq!(profile.button_index(5), Some(1));
+	assert!(toml::from_str::<HardwareProfile>("buzer = 13").is_err());
+}
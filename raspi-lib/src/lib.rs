@@ -24,15 +24,40 @@ pub use embedded_graphics::text::Text;
 pub use embedded_graphics::Drawable;
 pub use embedded_graphics::primitives::Rectangle;
 
+mod burn_in;
+pub use burn_in::BurnInPolicy;
+
+mod clock;
+pub use clock::{Clock, FakeClock, SystemClock};
+
 mod context;
 pub use context::Draw;
 
+mod display;
+pub use display::{Display, DisplayError, Oled, Panel};
+
+mod hardware;
+pub use hardware::{ButtonPin, DisplayPins, HardwareError, HardwareProfile, PinBias, PinEdge};
+
+mod locale;
+pub use locale::Locale;
+
 mod screensaver;
 pub use screensaver::Screensaver;
 
+mod simple_screensaver;
+pub use simple_screensaver::{SPEED, SimpleScreensaver};
+
+mod text;
+pub use text::{TextNotification, draw_marquee, marquee, wrap};
+
 mod time_display;
 pub use time_display::TimeDisplay;
 
+mod timezone;
+pub use time_tz::Tz;
+pub use timezone::{DEFAULT_TIMEZONE, local_time, timezone};
+
 pub fn new_rng() -> Rng {
 	let seed = getrandom::u64().unwrap();
 	Rng::seed_from_u64(seed)
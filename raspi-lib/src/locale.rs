@@ -0,0 +1,153 @@
+//! Weekday names, date and time formats.
+
+use serde::Deserialize;
+use time::{Date, Month, Time, Weekday};
+
+/// Language of the weekday and month names and the order of dates.
+/// German, the default, and French use a 24 hour clock, English a 12 hour clock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+	#[default]
+	De,
+	En,
+	Fr,
+}
+
+impl Locale {
+	/// Look up a locale by its name as in the configuration, e.g. `en`.
+	pub fn from_name(name: &str) -> Option<Locale> {
+		Some(match name {
+			"de" => Locale::De,
+			"en" => Locale::En,
+			"fr" => Locale::Fr,
+			_ => return None,
+		})
+	}
+
+	/// Two letter abbreviation.
+	pub fn weekday(self, day: Weekday) -> &'static str {
+		let names = match self {
+			Locale::De => ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+			Locale::En => ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"],
+			Locale::Fr => ["Lu", "Ma", "Me", "Je", "Ve", "Sa", "Di"],
+		};
+		names[day.number_days_from_monday() as usize]
+	}
+
+	/// Full name, not necessarily ASCII.
+	pub fn month(self, month: Month) -> &'static str {
+		let names = match self {
+			Locale::De => [
+				"Januar",
+				"Februar",
+				"März",
+				"April",
+				"Mai",
+				"Juni",
+				"Juli",
+				"August",
+				"September",
+				"Oktober",
+				"November",
+				"Dezember",
+			],
+			Locale::En => [
+				"January",
+				"February",
+				"March",
+				"April",
+				"May",
+				"June",
+				"July",
+				"August",
+				"September",
+				"October",
+				"November",
+				"December",
+			],
+			Locale::Fr => [
+				"janvier",
+				"février",
+				"mars",
+				"avril",
+				"mai",
+				"juin",
+				"juillet",
+				"août",
+				"septembre",
+				"octobre",
+				"novembre",
+				"décembre",
+			],
+		};
+		names[month as usize - 1]
+	}
+
+	/// Day and month, like `3.5.` or `5/3`.
+	pub fn date(self, date: Date) -> String {
+		match self {
+			Locale::De => format!("{}.{}.", date.day(), date.month() as u8),
+			Locale::En => format!("{}/{}", date.month() as u8, date.day()),
+			Locale::Fr => format!("{}/{}", date.day(), date.month() as u8),
+		}
+	}
+
+	pub fn twelve_hour(self) -> bool {
+		self == Locale::En
+	}
+
+	/// Hour as shown on the clock, with `am` or `pm` on a 12 hour clock.
+	pub fn hour(self, hour: u8) -> (u8, Option<&'static str>) {
+		if !self.twelve_hour() {
+			return (hour, None);
+		}
+		let suffix = if hour < 12 { "am" } else { "pm" };
+		((hour + 11) % 12 + 1, Some(suffix))
+	}
+
+	/// Hours and minutes, like `18:30` or `6:30pm`.
+	pub fn time(self, time: Time) -> String {
+		match self.hour(time.hour()) {
+			(hour, Some(suffix)) => format!("{}:{:02}{}", hour, time.minute(), suffix),
+			(hour, None) => format!("{:02}:{:02}", hour, time.minute()),
+		}
+	}
+
+	/// Short time span until an event, like `1h5m`.
+	pub fn countdown(self, hours: i64, minutes: i64) -> String {
+		match (self, hours) {
+			(Locale::Fr, 0) => format!("{minutes}min"),
+			(Locale::Fr, _) => format!("{hours}h{minutes:02}"),
+			(_, 0) => format!("{minutes}m"),
+			_ => format!("{hours}h{minutes}m"),
+		}
+	}
+
+	/// Label of events lasting whole days, `until` is the last day if there are several.
+	pub fn all_day(self, until: Option<Date>) -> String {
+		match (self, until) {
+			(Locale::De, None) => "ganztägig".to_owned(),
+			(Locale::De, Some(date)) => format!("bis {}", self.date(date)),
+			(Locale::En, None) => "all day".to_owned(),
+			(Locale::En, Some(date)) => format!("until {}", self.date(date)),
+			(Locale::Fr, None) => "toute la journée".to_owned(),
+			(Locale::Fr, Some(date)) => format!("jusqu'au {}", self.date(date)),
+		}
+	}
+}
+
+#[test]
+fn test_locale() {
+	use time::macros::{date, time};
+
+	assert_eq!(Locale::De.weekday(Weekday::Sunday), "So");
+	assert_eq!(Locale::Fr.weekday(Weekday::Monday), "Lu");
+	assert_eq!(Locale::De.date(date!(2024 - 05 - 03)), "3.5.");
+	assert_eq!(Locale::En.date(date!(2024 - 05 - 03)), "5/3");
+	assert_eq!(Locale::En.time(time!(0:05)), "12:05am");
+	assert_eq!(Locale::En.time(time!(18:30)), "6:30pm");
+	assert_eq!(Locale::Fr.time(time!(8:30)), "08:30");
+	assert_eq!(Locale::De.countdown(1, 5), "1h5m");
+	assert_eq!(Locale::Fr.countdown(1, 5), "1h05");
+}
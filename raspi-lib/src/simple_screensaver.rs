@@ -0,0 +1,140 @@
+use std::any::Any;
+use std::sync::atomic::{AtomicU32, AtomicU64};
+
+use embedded_graphics::prelude::RgbColor;
+use embedded_graphics::{
+	pixelcolor::Rgb565,
+	prelude::{DrawTarget, Point, Size},
+	primitives::{PrimitiveStyleBuilder, Rectangle, StyledDrawable},
+};
+use rand_xoshiro::rand_core::RngCore;
+
+use crate::{Draw, Rng, Screensaver};
+
+/// Pixels drawn per frame by a [`SimpleScreensaver`].
+pub static SPEED: AtomicU64 = AtomicU64::new(32);
+
+#[derive(Debug)]
+pub struct SimpleScreensaver {
+	id: &'static str,
+	data: &'static [u8],
+	iters: AtomicU32,
+}
+
+impl Clone for SimpleScreensaver {
+	fn clone(&self) -> Self {
+		Self {
+			id: self.id,
+			data: self.data,
+			iters: AtomicU32::new(self.iters.load(std::sync::atomic::Ordering::Relaxed)),
+		}
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Screensaver<D> for SimpleScreensaver {
+	fn id(&self) -> &'static str {
+		self.id
+	}
+
+	fn convert_draw(&self) -> Box<dyn Draw<D>> {
+		Box::new(self.clone())
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Draw<D> for SimpleScreensaver {
+	fn draw(&self, disp: &mut D, rng: &mut Rng) -> Result<bool, D::Error> {
+		for _ in 0..SPEED.load(std::sync::atomic::Ordering::Relaxed) {
+			let x = (rng.next_u32() % 128) as usize;
+			let y = (rng.next_u32() % 128) as usize;
+			let dx = (rng.next_u32() % 8) as i32 - 4;
+			let dy = (rng.next_u32() % 8) as i32 - 4;
+			let red = self.data[y * 128 * 3 + x * 3 + 0];
+			let green = self.data[y * 128 * 3 + x * 3 + 1];
+			let blue = self.data[y * 128 * 3 + x * 3 + 2];
+			if red | green | blue != 0 {
+				let color = rng.next_u32();
+				let r;
+				let g;
+				let b;
+				r = (red >> 3).saturating_add(color as u8 & 0b11).min(0b11111);
+				g = (green >> 2).saturating_add(((color >> 2) & 0b11) as u8).min(0b111111);
+				b = (blue >> 3).saturating_add(((color >> 4) & 0b11) as u8).min(0b11111);
+				let p = Rectangle::new(Point::new(x as i32 + dx, y as i32 + dy), Size::new(1, 1));
+				let s = PrimitiveStyleBuilder::new().fill_color(Rgb565::new(r, g, b)).build();
+				p.draw_styled(&s, disp)?;
+			}
+		}
+		self.iters.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+		Ok(true)
+	}
+
+	fn expired(&self) -> bool {
+		self.iters.load(std::sync::atomic::Ordering::Relaxed) > 1000
+	}
+
+	fn name(&self) -> &'static str {
+		self.id
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+impl SimpleScreensaver {
+	/// `data` is a 128x128 RGB image.
+	pub const fn new(id: &'static str, data: &'static [u8]) -> Self {
+		if data.len() != 128 * 128 * 3 {
+			panic!("invalid screensaver size");
+		}
+		SimpleScreensaver {
+			id,
+			data,
+			iters: AtomicU32::new(0),
+		}
+	}
+
+	pub fn get_pixel(&self, x: u32, y: u32) -> Rgb565 {
+		let idx = y as usize * 128 + x as usize;
+		let (red, green, blue) = (self.data[3 * idx], self.data[3 * idx + 1], self.data[3 * idx + 2]);
+		Rgb565::new(red >> 3, green >> 2, blue >> 3)
+	}
+
+	pub fn draw_all_colored<D: DrawTarget<Color = Rgb565>>(&self, disp: &mut D, color: Rgb565) -> Result<(), D::Error> {
+		disp.fill_contiguous(
+			&Rectangle::new((0, 0).into(), (128, 128).into()),
+			(0..128 * 128).map(|idx| {
+				let (red, green, blue) = (self.data[3 * idx], self.data[3 * idx + 1], self.data[3 * idx + 2]);
+				let r = red >> 3;
+				let g = green >> 2;
+				let b = blue >> 3;
+				if (r, g, b) != (0, 0, 0) { color } else { Rgb565::BLACK }
+			}),
+		)?;
+		Ok(())
+	}
+
+	pub fn draw_all<D: DrawTarget<Color = Rgb565>>(&self, disp: &mut D, flipped: bool) -> Result<(), D::Error> {
+		disp.fill_contiguous(
+			&Rectangle::new((0, 0).into(), (128, 128).into()),
+			(0..128 * 128).map(|idx| {
+				let (mut red, mut green, mut blue) =
+					(self.data[3 * idx], self.data[3 * idx + 1], self.data[3 * idx + 2]);
+				if flipped {
+					red = 255 - red;
+					green = 255 - green;
+					blue = 255 - blue;
+				}
+				let r = red >> 3;
+				let g = green >> 2;
+				let b = blue >> 3;
+				Rgb565::new(r, g, b)
+			}),
+		)?;
+		Ok(())
+	}
+}
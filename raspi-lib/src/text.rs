@@ -0,0 +1,261 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::iter;
+
+use embedded_graphics::mono_font::{MonoFont, MonoTextStyle, iso_8859_10::FONT_8X13};
+use embedded_graphics::prelude::{DrawTarget, Point, RgbColor};
+use embedded_graphics::text::{Baseline, Text};
+use embedded_graphics::{Drawable, Pixel, pixelcolor::Rgb565};
+
+use crate::{Draw, Rng, SimpleScreensaver};
+
+/// Window of `width` characters into `text` at scroll position `step`.
+/// Text that fits is returned unchanged, longer text scrolls in from the right and out to the left.
+pub fn marquee(text: &str, width: usize, step: usize) -> String {
+	if text.chars().count() <= width {
+		return text.to_owned();
+	}
+	let padded: Vec<char> = iter::repeat_n(' ', width)
+		.chain(text.chars())
+		.chain(iter::repeat_n(' ', width + 2))
+		.collect();
+	let start = step % (padded.len() - width);
+	padded[start..start + width].iter().collect()
+}
+
+/// Draw [`marquee`] at `position`. Returns whether the text scrolls, i.e. changes with `step`.
+pub fn draw_marquee<D: DrawTarget<Color = Rgb565>>(
+	disp: &mut D,
+	text: &str,
+	position: Point,
+	width: usize,
+	step: usize,
+	style: MonoTextStyle<'_, Rgb565>,
+) -> Result<bool, D::Error> {
+	Text::new(&marquee(text, width, step), position, style).draw(disp)?;
+	Ok(text.chars().count() > width)
+}
+
+/// Greedy word wrap to lines of at most `width` characters. Longer words are split.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+	let mut lines = vec![];
+	for paragraph in text.lines() {
+		let mut line = String::new();
+		for word in paragraph.split_whitespace() {
+			let mut word: Vec<char> = word.chars().collect();
+			while word.len() > width {
+				if !line.is_empty() {
+					lines.push(std::mem::take(&mut line));
+				}
+				lines.push(word.drain(..width).collect());
+			}
+			let line_len = line.chars().count();
+			if line_len != 0 && line_len + 1 + word.len() > width {
+				lines.push(std::mem::take(&mut line));
+			}
+			if !line.is_empty() {
+				line.push(' ');
+			}
+			line.extend(word);
+		}
+		lines.push(line);
+	}
+	lines
+}
+
+/// Full-screen text with optional title and icon.
+///
+/// Body lines that do not fit scroll horizontally (one character per frame).
+#[derive(Debug, Clone)]
+pub struct TextNotification {
+	title: Option<String>,
+	body: String,
+	icon: Option<&'static SimpleScreensaver>,
+	font: &'static MonoFont<'static>,
+	color: Rgb565,
+	wrap: bool,
+	/// Frames until the notification expires.
+	timeout: Option<u32>,
+	priority: u8,
+	calls: Cell<u32>,
+	/// Whether the display still shows the last frame.
+	drawn: Cell<bool>,
+}
+
+/// Size of the downscaled icon in pixels.
+const ICON_SIZE: i32 = 32;
+
+impl TextNotification {
+	pub fn new(body: impl Into<String>) -> Self {
+		TextNotification {
+			title: None,
+			body: body.into(),
+			icon: None,
+			font: &FONT_8X13,
+			color: Rgb565::WHITE,
+			wrap: true,
+			timeout: None,
+			priority: 0,
+			calls: Cell::new(0),
+			drawn: Cell::new(false),
+		}
+	}
+
+	pub fn with_title(mut self, title: impl Into<String>) -> Self {
+		self.title = Some(title.into());
+		self
+	}
+
+	/// Show the screensaver image, scaled down to 32x32, in the top left corner.
+	pub fn with_icon(mut self, icon: &'static SimpleScreensaver) -> Self {
+		self.icon = Some(icon);
+		self
+	}
+
+	pub fn with_font(mut self, font: &'static MonoFont<'static>) -> Self {
+		self.font = font;
+		self
+	}
+
+	pub fn with_color(mut self, color: Rgb565) -> Self {
+		self.color = color;
+		self
+	}
+
+	/// Disable word wrapping: every body line is shown as is, scrolling if necessary.
+	pub fn without_wrap(mut self) -> Self {
+		self.wrap = false;
+		self
+	}
+
+	/// Expire after `frames` frames.
+	pub fn with_timeout(mut self, frames: u32) -> Self {
+		self.timeout = Some(frames);
+		self
+	}
+
+	/// See [`Draw::priority`].
+	pub fn with_priority(mut self, priority: u8) -> Self {
+		self.priority = priority;
+		self
+	}
+
+	fn char_width(&self) -> i32 {
+		(self.font.character_size.width + self.font.character_spacing) as i32
+	}
+
+	fn line_height(&self) -> i32 {
+		self.font.character_size.height as i32 + 1
+	}
+
+	fn title_width(&self, screen_width: i32) -> usize {
+		let offset = if self.icon.is_some() { ICON_SIZE + 4 } else { 0 };
+		((screen_width - offset) / self.char_width()) as usize
+	}
+
+	fn body_lines(&self, width: usize) -> Vec<String> {
+		if self.wrap {
+			wrap(&self.body, width)
+		} else {
+			self.body.lines().map(str::to_owned).collect()
+		}
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Draw<D> for TextNotification {
+	fn draw(&self, disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
+		let step = self.calls.get() as usize;
+		self.calls.set(self.calls.get() + 1);
+		let size = disp.bounding_box().size;
+		let (screen_width, screen_height) = (size.width as i32, size.height as i32);
+		let width = (screen_width / self.char_width()) as usize;
+		let lines = self.body_lines(width);
+		let scrolls = lines.iter().any(|x| x.chars().count() > width)
+			|| self
+				.title
+				.as_ref()
+				.is_some_and(|x| x.chars().count() > self.title_width(screen_width));
+		if self.drawn.replace(true) && !scrolls {
+			return Ok(false);
+		}
+		disp.clear(Rgb565::BLACK)?;
+		let style = MonoTextStyle::new(self.font, self.color);
+		let mut y = 0;
+		if let Some(icon) = self.icon {
+			let scale = 128 / ICON_SIZE;
+			disp.draw_iter((0..ICON_SIZE * ICON_SIZE).map(|idx| {
+				let (x, y) = (idx % ICON_SIZE, idx / ICON_SIZE);
+				Pixel(Point::new(x, y), icon.get_pixel((x * scale) as u32, (y * scale) as u32))
+			}))?;
+		}
+		if let Some(title) = &self.title {
+			let x = if self.icon.is_some() { ICON_SIZE + 4 } else { 0 };
+			let title_y = if self.icon.is_some() {
+				(ICON_SIZE - self.line_height()) / 2
+			} else {
+				0
+			};
+			Text::with_baseline(
+				&marquee(title, self.title_width(screen_width), step),
+				Point::new(x, title_y),
+				style,
+				Baseline::Top,
+			)
+			.draw(disp)?;
+			y = self.line_height();
+		}
+		if self.icon.is_some() {
+			y = ICON_SIZE + 2;
+		}
+		for line in lines {
+			if y + self.line_height() > screen_height {
+				break;
+			}
+			Text::with_baseline(&marquee(&line, width, step), Point::new(0, y), style, Baseline::Top).draw(disp)?;
+			y += self.line_height();
+		}
+		Ok(true)
+	}
+
+	fn expired(&self) -> bool {
+		self.timeout.is_some_and(|x| self.calls.get() > x)
+	}
+
+	fn redraw(&mut self) {
+		self.drawn.set(false);
+	}
+
+	fn priority(&self) -> u8 {
+		self.priority
+	}
+
+	fn name(&self) -> &'static str {
+		"text"
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+#[test]
+fn test_marquee() {
+	assert_eq!(marquee("short", 8, 3), "short");
+	assert_eq!(marquee("0123456789", 4, 0), "    ");
+	assert_eq!(marquee("0123456789", 4, 5), "1234");
+	// wraps around after scrolling out completely
+	assert_eq!(marquee("0123456789", 4, 16), "    ");
+}
+
+#[test]
+fn test_wrap() {
+	assert_eq!(
+		wrap("Waschmaschine ist fertig\nabholen", 16),
+		["Waschmaschine", "ist fertig", "abholen"]
+	);
+	assert_eq!(wrap("0123456789abcdefXY z", 16), ["0123456789abcdef", "XY z"]);
+}
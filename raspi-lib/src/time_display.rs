@@ -1,33 +1,42 @@
 use std::any::Any;
 use std::cell::RefCell;
+use std::rc::Rc;
 
 use embedded_graphics::Drawable;
+use embedded_graphics::mono_font::{MonoTextStyle, ascii::FONT_6X9};
 use embedded_graphics::prelude::Point;
 use embedded_graphics::text::Text;
 use embedded_graphics::{mono_font::MonoTextStyleBuilder, pixelcolor::Rgb565, prelude::DrawTarget};
 use time::{Duration, OffsetDateTime};
-use time_tz::{OffsetDateTimeExt, timezones::db::europe::BERLIN};
+use time_tz::{OffsetDateTimeExt, Tz};
 
-use crate::{Draw, FONT_10X20, Rng};
+use crate::{Clock, Draw, FONT_10X20, Locale, Rng};
 
 use super::Screensaver;
 
 static TIME_COLOR: Rgb565 = Rgb565::new(0b01_111, 0b011_111, 0b01_111);
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct TimeDisplay {
 	last_min: RefCell<OffsetDateTime>,
+	tz: &'static Tz,
+	clock: Rc<dyn Clock>,
+	locale: Locale,
 }
 
 impl TimeDisplay {
-	pub fn new() -> Self {
+	pub fn new(tz: &'static Tz, clock: Rc<dyn Clock>, locale: Locale) -> Self {
 		TimeDisplay {
 			last_min: RefCell::new(
-				OffsetDateTime::now_utc()
-					.to_timezone(BERLIN)
+				clock
+					.now_utc()
+					.to_timezone(tz)
 					.checked_sub(Duration::minutes(2))
 					.unwrap(),
 			),
+			tz,
+			clock,
+			locale,
 		}
 	}
 
@@ -49,7 +58,7 @@ impl<D: DrawTarget<Color = Rgb565>> Screensaver<D> for TimeDisplay {
 
 impl<D: DrawTarget<Color = Rgb565>> Draw<D> for TimeDisplay {
 	fn draw(&self, disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
-		let time = OffsetDateTime::now_utc().to_timezone(BERLIN);
+		let time = self.clock.now_utc().to_timezone(self.tz);
 		if time.minute() == self.last_min.borrow().minute() {
 			return Ok(false);
 		}
@@ -76,7 +85,17 @@ impl<D: DrawTarget<Color = Rgb565>> Draw<D> for TimeDisplay {
 		let minute = time.minute();
 		let unix_minutes = minute as i32 * 5 / 3; // (time.unix_timestamp() / 60) as i32;
 		let dx = ((hour % 3) as i32 - 1) * 40 - 2;
-		let hour = format!("{:02}", hour);
+		let hour = match self.locale.hour(hour) {
+			(hour, Some(suffix)) => {
+				// below the minutes, above them near the bottom of the screen
+				let y = 20 + unix_minutes % 100;
+				let y = if y + 10 > 126 { y - 16 } else { y + 10 };
+				let style = MonoTextStyle::new(&FONT_6X9, TIME_COLOR);
+				Text::new(suffix, Point::new(64 + 9 + dx, y), style).draw(disp)?;
+				format!("{:2}", hour)
+			},
+			(hour, None) => format!("{:02}", hour),
+		};
 		Text::new(
 			&hour,
 			Point::new(64 - 20 + dx, 20 + unix_minutes % 100),
@@ -94,6 +113,14 @@ impl<D: DrawTarget<Color = Rgb565>> Draw<D> for TimeDisplay {
 		Ok(true)
 	}
 
+	fn redraw(&mut self) {
+		TimeDisplay::redraw(self);
+	}
+
+	fn name(&self) -> &'static str {
+		"time"
+	}
+
 	fn as_any(&self) -> &dyn Any {
 		self
 	}
@@ -0,0 +1,51 @@
+use time::{Date, Duration, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+use time_tz::{OffsetResult, PrimitiveDateTimeExt, Tz, timezones};
+
+/// Time zone used if none is configured.
+pub static DEFAULT_TIMEZONE: &Tz = timezones::db::europe::BERLIN;
+
+/// Look up a time zone by its IANA name, e.g. `Europe/Berlin`.
+pub fn timezone(name: &str) -> Option<&'static Tz> {
+	timezones::get_by_name(name)
+}
+
+/// Resolve a wall clock time in the given time zone.
+///
+/// Ambiguous times (when DST ends) resolve to the earlier instant.
+/// Times skipped when DST starts are shifted forward by the length of the gap.
+pub fn local_time(date: Date, time: Time, tz: &Tz) -> OffsetDateTime {
+	let dt = PrimitiveDateTime::new(date, time);
+	match dt.assume_timezone(tz) {
+		OffsetResult::Some(x) | OffsetResult::Ambiguous(x, _) => x,
+		OffsetResult::None => {
+			// use the offset in effect before the gap
+			let offset = (dt - Duration::days(1))
+				.assume_timezone(tz)
+				.take_first()
+				.map(|x| x.offset())
+				.unwrap_or(UtcOffset::UTC);
+			dt.assume_offset(offset)
+		},
+	}
+}
+
+#[test]
+fn test_local_time_dst() {
+	use time::macros::{date, datetime, time};
+
+	let tz = timezone("Europe/Berlin").unwrap();
+	assert_eq!(
+		local_time(date!(2024 - 07 - 01), time!(11:40), tz),
+		datetime!(2024-07-01 11:40 +2)
+	);
+	// 02:30 does not exist on 2024-03-31
+	assert_eq!(
+		local_time(date!(2024 - 03 - 31), time!(02:30), tz),
+		datetime!(2024-03-31 03:30 +2)
+	);
+	// 02:30 happens twice on 2024-10-27
+	assert_eq!(
+		local_time(date!(2024 - 10 - 27), time!(02:30), tz),
+		datetime!(2024-10-27 02:30 +2)
+	);
+}
@@ -1,8 +1,7 @@
 #![feature(round_char_boundary)]
 
-use std::{fmt::Debug, fs, ops::Sub, time::Duration};
+use std::{fmt::Debug, fs, path::Path};
 
-use display_interface_spi::SPIInterface;
 use embedded_graphics::{
 	draw_target::DrawTarget,
 	mono_font::{
@@ -16,14 +15,15 @@ use embedded_graphics::{
 	Drawable,
 };
 
-use raspi_oled::Events;
-use rppal::{
-	gpio::Gpio,
-	spi::{Bus, Mode, SimpleHalSpiDevice, SlaveSelect, Spi},
+use raspi_lib::{Display, Locale};
+use raspi_oled::{
+	config::Config,
+	events::UPCOMING_DAYS,
+	storage::{Measurement, Quantity, Storage, DEFAULT_SENSOR},
+	Events,
 };
-use rusqlite::Connection;
-use time::{format_description, Date, OffsetDateTime, PrimitiveDateTime};
-use time_tz::{timezones::db::europe::BERLIN, OffsetDateTimeExt, PrimitiveDateTimeExt};
+use time::{OffsetDateTime, Time, Weekday};
+use time_tz::{OffsetDateTimeExt, Tz};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum Status {
@@ -47,30 +47,31 @@ impl Status {
 fn main() {
 	let args = std::env::args().collect::<Vec<_>>();
 	if args.len() < 4 {
-		panic!("missing argument: database path, event JSON data file, events / temps");
+		panic!("missing argument: database path, events (JSON, .ics or directory), events / temps, [time zone]");
 	}
-	let database = Connection::open(&args[1]).expect("failed to open database");
-	let events = fs::read_to_string(&args[2]).expect("failed to read events.json");
-	let events: Events = serde_json::from_str(&events).unwrap();
+	let tz = match args.get(4) {
+		Some(name) => raspi_lib::timezone(name).expect("unknown time zone"),
+		None => raspi_lib::DEFAULT_TIMEZONE,
+	};
+	let database = Storage::open(Path::new(&args[1])).expect("failed to open database");
+	let mut events =
+		Events::load(Path::new(&args[2]), tz, OffsetDateTime::now_utc()).unwrap_or_else(|e| panic!("{}", e));
 
-	let (rh, temp): (i64, i64) = database
-		.query_row(
-			"SELECT humidity, celsius FROM sensor_readings ORDER BY sensor_readings.time DESC LIMIT 1",
-			[],
-			|row| Ok((row.get(0).unwrap(), row.get(1).unwrap())),
-		)
-		.unwrap();
+	let latest = |quantity| {
+		database
+			.latest(DEFAULT_SENSOR, quantity)
+			.unwrap()
+			.map_or(0, |x| x.tenths() as i64)
+	};
+	let (rh, temp) = (latest(Quantity::Humidity), latest(Quantity::Temperature));
 
-	let time = OffsetDateTime::now_utc().to_timezone(BERLIN);
+	let time = OffsetDateTime::now_utc().to_timezone(tz);
 
-	let mut query = database
-		.prepare("SELECT celsius FROM sensor_readings ORDER BY sensor_readings.time DESC LIMIT 288")
-		.unwrap();
-	let mut temps: Vec<i32> = query
-		.query_map([], |r| Ok(r.get(0)))
+	let mut temps: Vec<i32> = database
+		.recent(DEFAULT_SENSOR, Quantity::Temperature, 288)
 		.unwrap()
-		.map(Result::unwrap)
-		.map(Result::unwrap)
+		.iter()
+		.map(Measurement::tenths)
 		.collect();
 	let mut global_min = 1000;
 	let mut global_max = 0;
@@ -119,15 +120,24 @@ fn main() {
 		[Status::Unknown, Status::Unknown, Status::Unknown]
 	};
 
-	let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 19660800, Mode::Mode0).unwrap();
-	let gpio = Gpio::new().unwrap();
-	let dc = gpio.get(25).unwrap().into_output();
-	let spii = SPIInterface::new(SimpleHalSpiDevice::new(spi), dc);
-	let disp = ssd1351::display::display::Ssd1351::new(spii);
+	let config = Config::from_args(&args).expect("invalid configuration");
+	events.vacations = config.vacations.clone();
+	let disp = Display::open(&config.hardware.display).unwrap_or_else(|e| panic!("{}", e));
 	//let mut disp = FrameOutput::new(128, 128);
 
 	let mut disp = draw(
-		disp, time, rh, temp, events, &args, global_min, global_max, vals, status,
+		disp,
+		time,
+		tz,
+		config.locale,
+		rh,
+		temp,
+		events,
+		&args,
+		global_min,
+		global_max,
+		vals,
+		status,
 	);
 	let _ = disp.flush();
 	//disp.buffer.save("/tmp/x.png");
@@ -136,6 +146,8 @@ fn main() {
 fn draw<D: DrawTarget<Color = Rgb565>>(
 	mut disp: D,
 	time: OffsetDateTime,
+	tz: &Tz,
+	locale: Locale,
 	rh: i64,
 	temp: i64,
 	events: Events,
@@ -251,76 +263,32 @@ where
 		.into_styled(rect_style)
 		.draw(&mut disp)
 		.unwrap();
-	let mut day = time.weekday().number_days_from_monday() as usize;
-	let days = [
-		("M", "o"),
-		("D", "i"),
-		("M", "i"),
-		("D", "o"),
-		("F", "r"),
-		("S", "a"),
-		("S", "o"),
-	];
+	// first letter of the weekday large, the second one small
+	let weekday = |day: Weekday| locale.weekday(day).split_at(1);
+	let mut day = time.weekday();
 	for i in 0..5 {
-		Text::new(days[day].0, (x + 12 * i + 4, y + 6).into(), text_style_6x9)
+		let (first, second) = weekday(day);
+		Text::new(first, (x + 12 * i + 4, y + 6).into(), text_style_6x9)
 			.draw(&mut disp)
 			.unwrap();
-		Text::new(days[day].1, (x + 12 * i + 10, y + 6).into(), text_style4)
+		Text::new(second, (x + 12 * i + 10, y + 6).into(), text_style4)
 			.draw(&mut disp)
 			.unwrap();
-		day += 1;
-		day %= days.len();
+		day = day.next();
 	}
 	let mut bits = vec![];
 	// events
-	let mut all_events = vec![];
-	for event in events.weekly {
-		let mut event_time = time.clone();
-		while event_time.weekday().number_days_from_monday() as i32 != event.day {
-			event_time += Duration::from_secs(24 * 60 * 60);
-		}
-		all_events.push((
-			event.day,
-			event.hour,
-			event.minute,
-			event.duration,
-			event.name,
-			event_time.to_julian_day(),
-		));
-	}
-	let format = format_description::parse("[year]-[month]-[day]T[hour]:[minute]:[second]").unwrap();
-	for event in events.events {
-		let dt = PrimitiveDateTime::parse(&event.start_time, &format)
-			.unwrap()
-			.assume_timezone(BERLIN)
-			.unwrap();
-		let julian_day = dt.to_julian_day();
-		if dt < time {
-			continue;
-		}
-		let duration = if let Some(end_time) = event.end_time.as_ref() {
-			let dt2 = PrimitiveDateTime::parse(end_time, &format)
-				.unwrap()
-				.assume_timezone(BERLIN)
-				.unwrap();
-			(dt2.sub(dt).as_seconds_f32() / 60.0) as i32
-		} else {
-			30
-		};
-		all_events.push((
-			dt.weekday().number_days_from_monday() as _,
-			dt.hour() as _,
-			dt.minute() as _,
-			duration,
-			event.name,
-			julian_day,
-		));
+	let upcoming = events.upcoming(time, UPCOMING_DAYS, tz);
+	for e in &upcoming.errors {
+		eprintln!("warning: skipping {}", e);
 	}
-	let today = time.date().to_julian_day();
-	let weekday = time.weekday().number_days_from_monday() as i32;
-	all_events.sort_by_key(|x| (x.5, ((x.0 + 7) - weekday) % 7, x.1, x.2));
-	println!("{:?}", all_events);
-	let mut time_until_first = None;
+	let all_events = upcoming.occurrences;
+	let today = time.date();
+	let time_until_first = all_events
+		.iter()
+		.map(|x| x.start)
+		.find(|&x| x >= time)
+		.map(|x| (x - time).whole_seconds() as i32);
 	let colors = vec![
 		Rgb565::new(0xff >> 3, 0xff >> 2, 0x00 >> 3),
 		Rgb565::new(0xff >> 3, 0x00 >> 2, 0xff >> 3),
@@ -331,38 +299,17 @@ where
 		Rgb565::new(0xff >> 3, 0xff >> 2, 0xff >> 3),
 	];
 	for i in 0..5 {
-		let day = (weekday + i) % 7;
+		let midnight = raspi_lib::local_time(today + time::Duration::days(i as i64), Time::MIDNIGHT, tz);
 		for hour in 0..24 {
-			for minute in 0..60 {
-				if minute % 6 != 0 {
-					continue;
-				}
-
+			for minute in (0..60).step_by(6) {
 				if i == 0 && hour == time.hour() as i32 && minute == (time.minute() as i32 / 6) * 6 {
 					bits.push((i, hour, minute / 6, Some(Rgb565::new(0xff, 0x00, 0xff))));
 				}
-
+				let slot = midnight + time::Duration::minutes((hour * 60 + minute) as i64);
 				for (event_idx, event) in all_events.iter().enumerate() {
-					if event.0 != day || event.5 < today || event.5 - today > 4 {
-						continue;
-					}
-					let event_start = event.1 * 60 + event.2;
-					let event_end = event_start + event.3;
-					let now = hour * 60 + minute;
-					let now2 = hour * 60 + minute + 6;
-					if now2 > event_start && now < event_end {
+					if event.overlaps(slot, slot + time::Duration::minutes(6)) {
 						bits.push((i, hour, minute / 6, colors.get(event_idx).copied()));
 					}
-					if time_until_first.is_none()
-						&& (i > 0
-							|| event.1 > time.hour() as i32
-							|| (event.1 == time.hour() as i32 && event.2 >= time.minute() as i32))
-					{
-						time_until_first = Some(
-							((i * 24 + event.1) * 60 + event.2) * 60
-								- (time.hour() as i32 * 60 + time.minute() as i32) * 60,
-						);
-					}
 				}
 			}
 		}
@@ -380,28 +327,22 @@ where
 	}
 	if args[3] == "events" {
 		for (i, event) in all_events.iter().take(7).enumerate() {
-			let text = if event.4.len() > 19 {
-				&event.4[0..event.4.floor_char_boundary(19)]
+			let text = if event.name.len() > 19 {
+				&event.name[0..event.name.floor_char_boundary(19)]
 			} else {
-				&event.4
+				&event.name
 			};
-			let day = event.0 as usize;
+			let start = event.start.to_timezone(tz);
 			let y = y + 64 + 9 * i as i32 + 5;
-			if event.5 > today && event.5 - today > 7 {
-				let dt = Date::from_julian_day(event.5).unwrap();
-				Text::new(
-					&format!("{}.{}.", dt.day(), dt.month() as u8),
-					(0, y).into(),
-					text_style_4x6,
-				)
-				.draw(&mut disp)
-				.unwrap();
-			} else {
-				text_style_6x9.set_text_color(Some(Rgb565::new(0xff, 0xff, 0xff)));
-				Text::new(days[day].0, (x, y).into(), text_style_6x9)
+			if (start.date() - today).whole_days() > 7 {
+				Text::new(&locale.date(start.date()), (0, y).into(), text_style_4x6)
 					.draw(&mut disp)
 					.unwrap();
-				Text::new(days[day].1, (x + 6, y).into(), text_style4)
+			} else {
+				let (first, second) = weekday(start.weekday());
+				text_style_6x9.set_text_color(Some(Rgb565::new(0xff, 0xff, 0xff)));
+				Text::new(first, (x, y).into(), text_style_6x9).draw(&mut disp).unwrap();
+				Text::new(second, (x + 6, y).into(), text_style4)
 					.draw(&mut disp)
 					.unwrap();
 			}
@@ -446,10 +387,8 @@ where
 		let minutes = secs / 60 % 60;
 		let text = if days > 0 {
 			String::new()
-		} else if hours > 0 {
-			format!("{}h{}m", hours, minutes)
-		} else if minutes > 0 {
-			format!("{}m", minutes)
+		} else if hours > 0 || minutes > 0 {
+			locale.countdown(hours as i64, minutes as i64)
 		} else {
 			"?".into()
 		};
@@ -1,33 +1,27 @@
-use display_interface_spi::SPIInterface;
-use rppal::{
-	gpio::Gpio,
-	hal::Delay,
-	spi::{Bus, Mode, SimpleHalSpiDevice, SlaveSelect, Spi},
-};
+use raspi_lib::Display;
+use raspi_oled::config::Config;
 
 fn main() {
 	let args = std::env::args().collect::<Vec<_>>();
-	if args.len() < 2 {
-		panic!("missing argument: on/off");
+	let mut positional = vec![];
+	let mut iter = args.iter().skip(1);
+	while let Some(arg) = iter.next() {
+		if arg == "--config" {
+			iter.next();
+		} else {
+			positional.push(arg.as_str());
+		}
 	}
-	display_on_ssd1306(args[1] == "on");
+	let on = match positional[..] {
+		["on"] => true,
+		["off"] => false,
+		_ => panic!("usage: display_off [--config <path>] on|off"),
+	};
+	display_on_ssd1306(&args, on);
 }
 
-fn display_on_ssd1306(on: bool) {
-	let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 19660800, Mode::Mode0).unwrap();
-	let gpio = Gpio::new().unwrap();
-	let dc = gpio.get(25).unwrap().into_output();
-	let mut rst = gpio.get(27).unwrap().into_output();
-
-	// Init SPI
-	let spii = SPIInterface::new(SimpleHalSpiDevice::new(spi), dc);
-	let mut disp = ssd1351::display::display::Ssd1351::new(spii);
-
-	// Reset & init
-	disp.reset(&mut rst, &mut Delay).unwrap();
-	if on {
-		disp.turn_on().unwrap();
-	} else {
-		disp.turn_off().unwrap();
-	}
+fn display_on_ssd1306(args: &[String], on: bool) {
+	let hardware = Config::from_args(args).expect("invalid configuration").hardware;
+	let mut disp = Display::open(&hardware.display).unwrap_or_else(|e| panic!("{}", e));
+	disp.set_sleep(!on).unwrap();
 }
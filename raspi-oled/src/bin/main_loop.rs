@@ -1,35 +1,38 @@
 #![feature(array_windows)]
 
 use std::{
-	env, thread,
+	convert::TryFrom,
+	env, fs,
+	path::PathBuf,
+	process, thread,
 	time::{Duration, Instant},
 };
 
-use display_interface_spi::SPIInterface;
 use embedded_graphics::{pixelcolor::Rgb565, prelude::DrawTarget};
-use gpiocdev::line::{Bias, EdgeDetection, Value};
+use gpiocdev::line::Value;
 use rand_xoshiro::{rand_core::SeedableRng, Xoroshiro128StarStar};
+use raspi_lib::{Display, HardwareProfile};
 use raspi_oled::draw::Totp;
+use raspi_oled::PWM_ON;
 use raspi_oled::{
 	action::Action,
+	burn_in::BurnIn,
+	config::{Config, SCREENSAVER_IDS},
 	context::{Context, ContextDefault},
+	control::ControlServer,
+	headless::{Headless, FRAME_INTERVAL},
+	menu::{self, Button},
+	night_mode::NightMode,
 };
-use raspi_oled::{disable_pwm, enable_pwm, PWM_ON};
-use rppal::{
-	gpio::{Gpio, OutputPin},
-	hal::Delay,
-	spi::{Bus, Mode, SimpleHalSpiDevice, SlaveSelect, Spi},
-};
-use ssd1351::display::display::Ssd1351;
 
-pub type Oled = Ssd1351<SPIInterface<SimpleHalSpiDevice, OutputPin>>;
+pub type Oled = Display<raspi_lib::Oled>;
 
 static BLACK: Rgb565 = Rgb565::new(0, 0, 0);
-/// Delay after drawing a frame in milliseconds.
-const FRAME_INTERVAL: u64 = 66;
 
 fn main() {
-	if rppal::system::DeviceInfo::new().is_ok() {
+	if env::args().any(|x| x == "--headless") {
+		headless_main();
+	} else if rppal::system::DeviceInfo::new().is_ok() {
 		rpi_main();
 	} else {
 		pc_main();
@@ -45,15 +48,13 @@ fn pc_main() {
 
 	use winit::{
 		dpi::LogicalSize,
-		event::{Event, WindowEvent},
+		event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
 		event_loop::EventLoop,
 		window::WindowBuilder,
 	};
 
-	use raspi_oled::{
-		context::{Context, ContextDefault},
-		screensaver, FrameOutput,
-	};
+	use frame_output::FrameOutput;
+	use raspi_oled::screensaver;
 
 	let args: Vec<_> = env::args().map(|x| x.to_string()).collect();
 	for [key, val] in args.array_windows() {
@@ -75,10 +76,13 @@ fn pc_main() {
 
 	let start = Instant::now();
 	let mut iters = 0;
-	let mut disp = FrameOutput::new(128, 128);
+	let mut disp = Display::new(FrameOutput::new(128, 128));
 	let mut buffer_dirty = true;
 
-	let mut ctx = ContextDefault::new();
+	let config = config_from_args(&args);
+	let mut night = night_mode(&config);
+	let mut burn_in = config.burn_in();
+	let (mut ctx, mut control) = context_from_config(&config);
 	if args.iter().any(|x| x == "--totp") {
 		let pw = rpassword::prompt_password("TOTP password: ").unwrap();
 		let totps = andotp_import::read_from_file("./otp_accounts_2023-10-02_18-58-25.json.aes", &pw).unwrap();
@@ -100,6 +104,31 @@ fn pc_main() {
 				println!("The close button was pressed; stopping");
 				control_flow.set_exit();
 			},
+			// arrow keys and enter simulate the buttons
+			Event::WindowEvent {
+				event:
+					WindowEvent::KeyboardInput {
+						input:
+							KeyboardInput {
+								state: ElementState::Pressed,
+								virtual_keycode: Some(key),
+								..
+							},
+						..
+					},
+				..
+			} => {
+				let button = match key {
+					VirtualKeyCode::Up => Button::Up,
+					VirtualKeyCode::Down => Button::Down,
+					VirtualKeyCode::Return => Button::Select,
+					_ => return,
+				};
+				let now = ctx.now();
+				if !(night.wake(now, ctx.timezone()) | burn_in.input(now)) {
+					menu::press(&mut ctx, &mut disp, button);
+				}
+			},
 			Event::MainEventsCleared => {
 				// Application update code.
 
@@ -127,7 +156,14 @@ fn pc_main() {
 				// redraw
 				if Instant::now().duration_since(start) > Duration::from_millis(iters * FRAME_INTERVAL) {
 					iters += 1;
-					buffer_dirty = ctx.loop_iter(&mut disp, &mut rng);
+					let now = ctx.now();
+					let mut changed = night.apply(&mut ctx, &mut disp, now).unwrap_or(false);
+					changed |= burn_in.apply(&mut ctx, &mut disp, now).unwrap_or(false);
+					menu::close_stale(&mut ctx, &mut disp);
+					if let Some(control) = &mut control {
+						control.process(&mut ctx, &mut disp);
+					}
+					buffer_dirty = ctx.loop_iter(&mut disp, &mut rng) || changed;
 				}
 
 				let mut buffer = surface.buffer_mut().unwrap();
@@ -135,7 +171,7 @@ fn pc_main() {
 					for index in 0..(width * height) {
 						let y = index / width;
 						let x = index % width;
-						let pixel = disp.buffer.get_pixel(x, y);
+						let pixel = disp.panel().buffer.get_pixel(x, y);
 						let red = pixel.0[0] << 0;
 						let green = pixel.0[1] << 0;
 						let blue = pixel.0[2] << 0;
@@ -144,7 +180,6 @@ fn pc_main() {
 					}
 					buffer.present().unwrap();
 					buffer_dirty = false;
-					let _ = disp.buffer.save(format!("/tmp/iter{}.png", iters));
 				}
 			},
 			_ => (),
@@ -152,41 +187,150 @@ fn pc_main() {
 	});
 }
 
-fn rpi_main() {
+/// Render frames without a display: `--headless [--frames N] [--start YYYY-MM-DDTHH:MM] [--seed N]
+/// [--action <screensaver>] [--output <dir>] [--gif <path>]`.
+fn headless_main() {
 	let args: Vec<_> = env::args().map(|x| x.to_string()).collect();
+	let config = config_from_args(&args);
+	let mut frames = 100;
+	let mut start = None;
+	let mut seed = 17381;
+	let mut action = None;
+	let mut output = None;
+	let mut gif = None;
+	for [key, val] in args.array_windows() {
+		match key.as_str() {
+			"--frames" => frames = val.parse().expect("invalid --frames"),
+			"--start" => start = Some(val.clone()),
+			"--seed" => seed = val.parse().expect("invalid --seed"),
+			"--action" => {
+				action = Some(
+					*SCREENSAVER_IDS
+						.iter()
+						.find(|x| **x == val)
+						.expect("unknown screensaver for --action"),
+				)
+			},
+			"--output" => output = Some(PathBuf::from(val)),
+			"--gif" => gif = Some(PathBuf::from(val)),
+			"--speed" => {
+				raspi_oled::screensaver::SPEED.store(val.parse().unwrap(), std::sync::atomic::Ordering::Relaxed);
+			},
+			_ => {},
+		}
+	}
+	let tz = config.timezone().unwrap();
+	let start = match start {
+		Some(start) => parse_start(&start, tz).expect("invalid --start, expected YYYY-MM-DDTHH:MM"),
+		None => time::OffsetDateTime::now_utc(),
+	};
 
-	let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 19660800, Mode::Mode0).unwrap();
-	let gpio = Gpio::new().unwrap();
-	let dc = gpio.get(25).unwrap().into_output();
-	let mut rst = gpio.get(27).unwrap().into_output();
+	let mut headless = Headless::new(&config, start, seed).unwrap_or_else(|e| {
+		eprintln!("error: {e}");
+		process::exit(1);
+	});
+	if let Some(id) = action {
+		headless.do_action(Action::Screensaver(id));
+	}
+	if let Some(dir) = &output {
+		fs::create_dir_all(dir).unwrap();
+	}
+	let mut recorder = frame_output::GifRecorder::new();
+	headless.run(frames, |idx, disp, dirty| {
+		if !dirty && idx > 0 {
+			recorder.extend_last(FRAME_INTERVAL as u32);
+			return;
+		}
+		if let Some(dir) = &output {
+			disp.save_png(&dir.join(format!("frame{:05}.png", idx))).unwrap();
+		}
+		if gif.is_some() {
+			recorder.push(disp, FRAME_INTERVAL as u32);
+		}
+	});
+	if let Some(gif) = gif {
+		recorder.save(&gif).unwrap();
+	}
+}
 
-	// Init SPI
-	let spii = SPIInterface::new(SimpleHalSpiDevice::new(spi), dc);
-	let mut disp = Ssd1351::new(spii);
+/// Parse a local `YYYY-MM-DDTHH:MM` timestamp.
+fn parse_start(start: &str, tz: &raspi_lib::Tz) -> Option<time::OffsetDateTime> {
+	let (date, time) = start.split_once('T')?;
+	let mut date = date.splitn(3, '-').map(str::parse::<i32>);
+	let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+	let date = time::Date::from_calendar_date(year, time::Month::try_from(month as u8).ok()?, day as u8).ok()?;
+	let (hour, minute) = time.split_once(':')?;
+	let time = time::Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()?;
+	Some(raspi_lib::local_time(date, time, tz))
+}
 
-	// Reset & init display
-	disp.reset(&mut rst, &mut Delay).unwrap();
-	disp.turn_on().unwrap();
+fn rpi_main() {
+	let args: Vec<_> = env::args().map(|x| x.to_string()).collect();
+	let config = config_from_args(&args);
+	let hardware = config.hardware.clone();
+
+	let disp = Display::open(&hardware.display).unwrap_or_else(|e| {
+		eprintln!("error: {e}");
+		process::exit(1);
+	});
 
 	// Init PWM handling
-	let pwm = thread::spawn(handle_pwm);
+	let pwm = {
+		let hardware = hardware.clone();
+		thread::spawn(move || handle_pwm(&hardware))
+	};
 
-	let mut ctx = ContextDefault::new();
+	let night = night_mode(&config);
+	let burn_in = config.burn_in();
+	let (mut ctx, control) = context_from_config(&config);
 	if args.iter().any(|x| x == "--totp") {
 		let pw = rpassword::prompt_password("TOTP password: ").unwrap();
 		let totps = andotp_import::read_from_file("./otp_accounts_2023-10-02_18-58-25.json.aes", &pw).unwrap();
 		ctx.add(Totp::new(totps));
 	}
 
-	main_loop(disp, ctx);
+	main_loop(disp, ctx, control, night, burn_in, &hardware);
 
 	let _ = pwm.join();
 }
 
-fn handle_pwm() {
+/// Load the configuration file (see `--config`), exiting on errors.
+fn config_from_args(args: &[String]) -> Config {
+	Config::from_args(args).unwrap_or_else(|e| {
+		eprintln!("error: {e}");
+		process::exit(1);
+	})
+}
+
+/// Build the brightness schedule, exiting on errors.
+fn night_mode(config: &Config) -> NightMode {
+	config.night_mode().unwrap_or_else(|e| {
+		eprintln!("error: {e}");
+		process::exit(1);
+	})
+}
+
+/// Set up the context and the control API as described by the configuration.
+fn context_from_config<D: DrawTarget<Color = Rgb565>>(config: &Config) -> (ContextDefault<D>, Option<ControlServer>) {
+	let ctx = ContextDefault::new(config).unwrap_or_else(|e| {
+		eprintln!("error: {e}");
+		process::exit(1);
+	});
+	let control = config.listen.as_ref().map(|addr| match ControlServer::spawn(addr) {
+		Ok(control) => control,
+		Err(e) => {
+			eprintln!("error: failed to listen on {addr}: {e}");
+			process::exit(1);
+		},
+	});
+	(ctx, control)
+}
+
+fn handle_pwm(hardware: &HardwareProfile) {
+	let line = hardware.buzzer;
 	let pwm = gpiocdev::Request::builder()
-		.on_chip("/dev/gpiochip0")
-		.with_line(12)
+		.on_chip(&hardware.chip)
+		.with_line(line)
 		.as_output(Value::Inactive)
 		.request()
 		.unwrap();
@@ -194,125 +338,62 @@ fn handle_pwm() {
 		thread::sleep(Duration::from_millis(500));
 		let on = PWM_ON.load(std::sync::atomic::Ordering::Relaxed);
 		if !on {
-			let _ = pwm.set_value(12, Value::Inactive);
+			let _ = pwm.set_value(line, Value::Inactive);
 			continue;
 		}
 		for _ in 0..100 {
-			let _ = pwm.set_value(12, Value::Active);
+			let _ = pwm.set_value(line, Value::Active);
 			thread::sleep(Duration::from_millis(1));
-			let _ = pwm.set_value(12, Value::Inactive);
+			let _ = pwm.set_value(line, Value::Inactive);
 			thread::sleep(Duration::from_millis(1));
 		}
 	}
 }
 
-fn main_loop(mut disp: Oled, mut ctx: ContextDefault<Oled>) {
+fn main_loop(
+	mut disp: Oled,
+	mut ctx: ContextDefault<Oled>,
+	mut control: Option<ControlServer>,
+	mut night: NightMode,
+	mut burn_in: BurnIn,
+	hardware: &HardwareProfile,
+) {
 	disp.clear(BLACK).unwrap();
 
 	let mut rng = Xoroshiro128StarStar::seed_from_u64(17381);
-	let mut last_button = Instant::now();
-
-	let mut menu = vec![];
-	let lines = gpiocdev::Request::builder()
-		.on_chip("/dev/gpiochip0")
-		.with_line(19)
-		.with_edge_detection(EdgeDetection::RisingEdge)
-		.with_debounce_period(Duration::from_millis(5))
-		.with_bias(Bias::PullDown)
-		.with_line(6)
-		.with_edge_detection(EdgeDetection::RisingEdge)
-		.with_debounce_period(Duration::from_millis(5))
-		.with_bias(Bias::PullDown)
-		.with_line(5)
-		.with_edge_detection(EdgeDetection::RisingEdge)
-		.with_debounce_period(Duration::from_millis(5))
-		.with_bias(Bias::PullDown)
-		.request()
-		.unwrap();
+
+	let lines = hardware.request_buttons().unwrap();
 
 	loop {
 		// respond to button presses
 		while lines.wait_edge_event(Duration::from_millis(1)).unwrap() {
 			let e = lines.read_edge_event().unwrap();
-			last_button = Instant::now();
-			match e.offset {
-				5 => {
-					menu.push(1);
-				},
-				6 => {
-					menu.push(2);
-				},
-				19 => {
-					menu.push(3);
-				},
+			let button = match hardware.button_index(e.offset) {
+				Some(0) => Button::Up,
+				Some(1) => Button::Down,
+				Some(2) => Button::Select,
 				_ => {
 					println!("unknown offset: {}", e.offset);
+					continue;
 				},
-			}
-			let mut pop_last = false;
-			let mut clear = false;
-			match &*menu {
-				[1] => {
-					ctx.do_action(Action::Screensaver("measurements"));
-				},
-				[1, 2] => {
-					let _ = ctx.pop_action_and_clear(&mut disp);
-					ctx.do_action(Action::Screensaver("measurements_temps"));
-					pop_last = true;
-				},
-				[1, 3] => {
-					let _ = ctx.pop_action_and_clear(&mut disp);
-					ctx.do_action(Action::Screensaver("measurements_events"));
-					pop_last = true;
-				},
-				[2] => {
-					if ctx.active_count() > 1 {
-						let _ = ctx.pop_action_and_clear(&mut disp);
-						disable_pwm().unwrap();
-						let _ = disp.flush();
-						clear = true;
-					}
-				},
-				[3] => {
-					ctx.do_action(Action::Screensaver("totp"));
-				},
-				[3, 1] => {
-					if let Some(x) = ctx.active.borrow_mut().last_mut() {
-						let totp: Option<&mut Totp> = x.as_any_mut().downcast_mut();
-						if let Some(x) = totp {
-							x.next_page();
-						}
-					}
-					pop_last = true;
-				},
-				[3, 2, 1] => {
-					enable_pwm().unwrap();
-					pop_last = true;
-				},
-				[3, 2, 3] => {
-					disable_pwm().unwrap();
-					pop_last = true;
-				},
-				[3, 3] => {
-					ctx.do_action(Action::Screensaver("rpi"));
-					clear = true;
-				},
-				_ => {},
-			}
-			if pop_last {
-				menu.pop();
-			}
-			if clear {
-				menu.clear();
+			};
+			// the first press only turns the display on again
+			let now = ctx.now();
+			if !(night.wake(now, ctx.timezone()) | burn_in.input(now)) {
+				menu::press(&mut ctx, &mut disp, button);
 			}
 		}
-		// clean up stale menu selection
-		if !menu.is_empty() && Instant::now().duration_since(last_button).as_secs() >= 10 {
-			menu.clear();
+		let now = ctx.now();
+		let mut changed = night.apply(&mut ctx, &mut disp, now).unwrap_or(false);
+		changed |= burn_in.apply(&mut ctx, &mut disp, now).unwrap_or(false);
+		menu::close_stale(&mut ctx, &mut disp);
+		// execute remote commands
+		if let Some(control) = &mut control {
+			control.process(&mut ctx, &mut disp);
 		}
 		// run context loop
 		let dirty = ctx.loop_iter(&mut disp, &mut rng);
-		if dirty {
+		if dirty || changed {
 			let _ = disp.flush(); // ignore bus write errors, they are harmless
 		}
 		thread::sleep(Duration::from_millis(FRAME_INTERVAL));
@@ -1,27 +1,29 @@
-use std::{env, error::Error, fs};
+use std::{env, process};
 
-static WEEKLY: &'static str = include_str!("../../events_weekly.json");
+use raspi_oled::{config::Config, sync, Weekly};
+use time::OffsetDateTime;
+
+static WEEKLY: &str = include_str!("../../events_weekly.json");
 
 fn main() {
-	let args = env::args().skip(1).collect::<Vec<_>>();
-	let url = "http://nixos.fritz.box:12783/custom/event_alerts";
+	let args = env::args().collect::<Vec<_>>();
+	let config = Config::from_args(&args).unwrap_or_else(|e| exit(e));
+	let tz = config.timezone().unwrap_or_else(|e| exit(e));
 
-	if let Ok(json) = get_json(url) {
-		let mut buf = String::new();
-		buf += "{";
-		buf += "\"events\": ";
-		buf += &json;
-		buf += ",";
-		if args.len() >= 1 && args[0] == "--no-weekly" {
-			buf += r#""weekly": []"#;
-		} else {
-			buf += WEEKLY;
-		}
-		buf += "}";
-		fs::write("events.json", buf.as_bytes()).unwrap();
+	let mut events = config
+		.sync()
+		.fetch(tz, OffsetDateTime::now_utc())
+		.unwrap_or_else(|e| exit(e));
+	if !args.iter().any(|x| x == "--no-weekly") {
+		let weekly: Vec<Weekly> = serde_json::from_str(WEEKLY).expect("invalid events_weekly.json");
+		events.weekly.extend(weekly);
+	}
+	if let Err(e) = sync::write_events(&config.events, &events) {
+		exit(format_args!("failed to write {}: {}", config.events.display(), e));
 	}
 }
 
-fn get_json(url: &str) -> Result<String, Box<dyn Error>> {
-	Ok(ureq::get(url).call()?.into_body().read_to_string()?)
+fn exit(e: impl std::fmt::Display) -> ! {
+	eprintln!("error: {}", e);
+	process::exit(1);
 }
@@ -3,7 +3,6 @@ use std::{
 	time::{Duration, Instant},
 };
 
-use display_interface_spi::SPIInterface;
 use embedded_graphics::{
 	draw_target::DrawTarget,
 	mono_font::{ascii::FONT_10X20, MonoTextStyleBuilder},
@@ -12,11 +11,8 @@ use embedded_graphics::{
 	Drawable,
 };
 
-use rppal::{
-	gpio::Gpio,
-	hal::Delay,
-	spi::{Bus, Mode, SimpleHalSpiDevice, SlaveSelect, Spi},
-};
+use raspi_lib::Display;
+use raspi_oled::config::Config;
 //use ssd1351::{properties::DisplaySize, mode::{GraphicsMode, displaymode::DisplayModeTrait}};
 
 //use time_tz::{timezones::db::europe::BERLIN, OffsetDateTimeExt, PrimitiveDateTimeExt};
@@ -27,18 +23,9 @@ fn main() {
 	//let mut disp = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0).into_buffered_graphics_mode();
 	//disp.init().unwrap();
 	// Configure gpio
-	let spi = Spi::new(Bus::Spi0, SlaveSelect::Ss0, 19660800, Mode::Mode0).unwrap();
-	let gpio = Gpio::new().unwrap();
-	let dc = gpio.get(25).unwrap().into_output();
-	let mut rst = gpio.get(27).unwrap().into_output();
-
-	// Init SPI
-	let spii = SPIInterface::new(SimpleHalSpiDevice::new(spi), dc);
-	let mut disp = ssd1351::display::display::Ssd1351::new(spii);
-
-	// Reset & init
-	disp.reset(&mut rst, &mut Delay).unwrap();
-	disp.turn_on().unwrap();
+	let args = std::env::args().collect::<Vec<_>>();
+	let hardware = Config::from_args(&args).expect("invalid configuration").hardware;
+	let mut disp = Display::open(&hardware.display).unwrap_or_else(|e| panic!("{}", e));
 
 	/*
 	thread::sleep(Duration::from_secs(5));
@@ -75,6 +62,5 @@ fn main() {
 	println!("{:?} ms", start.elapsed().as_millis());
 	thread::sleep(Duration::from_secs(5));
 
-	disp.reset(&mut rst, &mut Delay).unwrap();
-	disp.turn_off().unwrap();
+	disp.set_sleep(true).unwrap();
 }
@@ -1,24 +1,19 @@
 use std::{
+	path::Path,
 	process::{Command, Stdio},
 	time::SystemTime,
 };
 
-use rusqlite::Connection;
+use raspi_oled::storage::Storage;
 
 fn main() {
 	let args = std::env::args().collect::<Vec<_>>();
 	if args.len() < 2 {
 		panic!("missing argument: database path");
 	}
-	let database = Connection::open(&args[1]).expect("failed to open database");
+	let database = Storage::open(Path::new(&args[1])).expect("failed to open database");
 
-	let timestamp: i64 = database
-		.query_row(
-			"SELECT time FROM sensor_readings ORDER BY time DESC LIMIT 1",
-			[],
-			|row| Ok(row.get(0).unwrap()),
-		)
-		.unwrap();
+	let timestamp = database.latest_time().unwrap().map_or(0, |x| x.unix_timestamp());
 
 	let time = std::time::SystemTime::now()
 		.duration_since(SystemTime::UNIX_EPOCH)
@@ -1,64 +1,65 @@
-use std::time::{Duration, SystemTime};
+use std::{path::Path, time::Duration};
 
-use rusqlite::{params, Connection};
+use raspi_oled::{
+	config::Config,
+	sensor::{sample, Am2302, EdgeMode, Sensor, Simulated},
+	storage::{Quality, Storage, DEFAULT_SENSOR},
+};
+use time::OffsetDateTime;
 
 fn main() {
 	let args = std::env::args().collect::<Vec<_>>();
 	if args.len() < 2 {
 		panic!("missing argument: database path");
 	}
-	let database = Connection::open(&args[1]).expect("failed to open database");
-	database
-		.execute(
-			"
-		CREATE TABLE IF NOT EXISTS sensor_readings(
-			time INTEGER PRIMARY KEY,
-			humidity INTEGER NOT NULL,
-			celsius INTEGER NOT NULL
-		)",
-			[],
-		)
-		.unwrap();
+	let arg = |name: &str| {
+		let idx = args.iter().position(|x| x == name)?;
+		Some(args.get(idx + 1).expect("missing argument value").as_str())
+	};
+	// --sensor <id>: store the readings under another sensor id, e.g. the room name
+	let sensor_id = arg("--sensor").unwrap_or(DEFAULT_SENSOR);
+	let database = Storage::open(Path::new(&args[1])).expect("failed to open database");
+	let time = OffsetDateTime::now_utc();
 
-	let mut attempts = 0;
-	let mut temps = vec![];
-	let mut rhs = vec![];
-	let time = std::time::SystemTime::now()
-		.duration_since(SystemTime::UNIX_EPOCH)
-		.unwrap();
-	while temps.len() < 5 && attempts < 10 {
-		if let Ok((rh, temp)) = raspi_oled::am2302_reading() {
-			// TODO: try out gpio_am2302_rs!
-			//if let Ok(reading) = gpio_am2302_rs::try_read(26) {
-			//let rh = reading.humidity as i64;
-			//let temp = (reading.temperature * 10.0) as i64;
-			if rh > 0 && temp < 500 {
-				rhs.push(rh);
-				temps.push(temp);
-			}
+	// --report: print the read statistics of the last day and week
+	if args.iter().any(|x| x == "--report") {
+		for (name, days) in [("day", 1), ("week", 7)] {
+			let stats = database
+				.stats_since(sensor_id, time - time::Duration::days(days))
+				.unwrap();
+			println!("last {}: {}", name, stats);
 		}
-		std::thread::sleep(Duration::from_secs(5));
-		attempts += 1;
+		return;
 	}
-	if !temps.is_empty() {
-		// median = hopefully no faulty readings
-		temps.sort();
-		rhs.sort();
-		let rh = rhs[rhs.len() / 2];
-		let temp = temps[temps.len() / 2];
-		println!(
-			"info: acquired {} readings (temps {:?}, rhs {:?}), using rh {} and temp {}",
-			temps.len(),
-			temps,
-			rhs,
-			rh,
-			temp
-		);
+
+	// --simulate <csv>: replay recorded readings instead of reading the AM2302
+	let (mut sensor, interval): (Box<dyn Sensor>, _) = match arg("--simulate") {
+		Some(path) => {
+			let sensor = Simulated::from_csv(Path::new(path)).unwrap_or_else(|e| panic!("{}: {}", path, e));
+			(Box::new(sensor), Duration::ZERO)
+		},
+		None => {
+			let hardware = Config::from_args(&args).expect("invalid configuration").hardware;
+			let mut am2302 = Am2302::new(hardware.chip.to_string_lossy(), hardware.am2302);
+			// --line <offset>: GPIO line the data pin is connected to, overrides the configuration
+			if let Some(line) = arg("--line") {
+				am2302.line = line.parse().expect("invalid GPIO line");
+			}
+			// --edge-events: record the response using kernel edge events instead of polling
+			if args.iter().any(|x| x == "--edge-events") {
+				am2302 = am2302.with_mode(EdgeMode::Events);
+			}
+			(Box::new(am2302), Duration::from_secs(5))
+		},
+	};
+
+	let (reading, stats) = sample(&mut *sensor, 5, 10, interval);
+	println!("info: {}", stats);
+	database.insert_stats(time, sensor_id, &stats).unwrap();
+	if let Some(reading) = reading {
+		println!("info: using {:?}", reading);
 		database
-			.execute(
-				"INSERT INTO sensor_readings (time, humidity, celsius) VALUES (?1, ?2, ?3)",
-				params![time.as_secs(), rh, temp],
-			)
+			.insert_reading(time, sensor_id, &reading, Quality::Good)
 			.unwrap();
 	}
 }
@@ -0,0 +1,121 @@
+//! Burn-in protection for every screen shown by [`ContextDefault`].
+
+use std::collections::HashMap;
+
+use raspi_lib::{BurnInPolicy, Display, DisplayError, Panel};
+use time::OffsetDateTime;
+
+use crate::context::ContextDefault;
+
+/// Applies the [`BurnInPolicy`] of the active screen.
+pub struct BurnIn {
+	default: BurnInPolicy,
+	/// Policies by [`Draw::name`](raspi_lib::Draw::name).
+	screens: HashMap<String, BurnInPolicy>,
+	/// Name of the active screen.
+	screen: Option<&'static str>,
+	/// Last button press or screen change.
+	idle_since: Option<OffsetDateTime>,
+	blanked: bool,
+}
+
+impl BurnIn {
+	pub fn new(default: BurnInPolicy, screens: HashMap<String, BurnInPolicy>) -> Self {
+		BurnIn {
+			default,
+			screens,
+			screen: None,
+			idle_since: None,
+			blanked: false,
+		}
+	}
+
+	pub fn policy(&self, screen: &str) -> BurnInPolicy {
+		self.screens.get(screen).copied().unwrap_or(self.default)
+	}
+
+	/// Register a button press.
+	/// Returns `true` if the display was blanked, in which case the press should only unblank it.
+	pub fn input(&mut self, time: OffsetDateTime) -> bool {
+		self.idle_since = Some(time);
+		self.blanked
+	}
+
+	/// Apply the policy of the active screen at `time` to `disp`, drawing the screen again if needed.
+	/// Returns whether the frame changed.
+	pub fn apply<P: Panel>(
+		&mut self,
+		ctx: &mut ContextDefault<Display<P>>,
+		disp: &mut Display<P>,
+		time: OffsetDateTime,
+	) -> Result<bool, DisplayError> {
+		let screen = ctx.active.borrow().last().map_or("", |x| x.name());
+		if self.screen != Some(screen) {
+			self.screen = Some(screen);
+			self.idle_since = Some(time);
+		}
+		let policy = self.policy(screen);
+		let minutes = time.unix_timestamp().div_euclid(60);
+		let mut redraw = disp.set_shift(policy.offset(minutes));
+		redraw |= disp.set_inverted(policy.inverted(minutes));
+		let idle = match (policy.idle_blank(), self.idle_since) {
+			(Some(limit), Some(since)) => time - since >= limit,
+			_ => false,
+		};
+		let blank_changed = idle != self.blanked;
+		self.blanked = idle;
+		redraw |= disp.set_blank(idle)?;
+		if redraw {
+			let _ = ctx.redraw(disp);
+		}
+		Ok(redraw || blank_changed)
+	}
+}
+
+impl Default for BurnIn {
+	fn default() -> Self {
+		BurnIn::new(BurnInPolicy::OFF, HashMap::new())
+	}
+}
+
+#[test]
+fn test_burn_in() {
+	use crate::{action::Action, config::Config, headless::Headless, menu::Button};
+	use embedded_graphics::prelude::Point;
+	use time::Duration;
+
+	let config: Config = toml::from_str(
+		r#"
+		database = ":memory:"
+		screensavers = ["star"]
+		schedule = []
+
+		[burn_in.default]
+		shift = 1
+		shift_minutes = 1
+		idle_blank_minutes = 10
+
+		[burn_in.screens.time]
+		shift = 0
+		"#,
+	)
+	.unwrap();
+	let start = OffsetDateTime::from_unix_timestamp(1_700_000_040).unwrap();
+	let mut headless = Headless::new(&config, start, 1).unwrap();
+	headless.step();
+	// the clock moves by itself
+	assert_eq!(headless.disp.shift(), Point::zero());
+	headless.do_action(Action::Screensaver("star"));
+	headless.step();
+	let first = headless.disp.shift();
+	headless.clock.set(start + Duration::minutes(1));
+	headless.step();
+	assert_ne!(headless.disp.shift(), first);
+	assert!(headless.disp.shift().x.abs() <= 1 && headless.disp.shift().y.abs() <= 1);
+
+	headless.clock.set(start + Duration::minutes(11));
+	headless.step();
+	assert!(headless.disp.is_blanked());
+	assert!(!headless.press(Button::Up));
+	assert!(!headless.disp.is_blanked());
+}
@@ -0,0 +1,618 @@
+//! Declarative setup of [`ContextDefault`](crate::context::ContextDefault).
+//!
+//! The configuration is a TOML file, see `config.example.toml` for all keys.
+
+use std::{
+	cell::RefCell,
+	collections::HashMap,
+	env, fmt, fs,
+	path::{Path, PathBuf},
+};
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::DrawTarget};
+use raspi_lib::{BurnInPolicy, HardwareProfile, Locale, Screensaver};
+use serde::Deserialize;
+use time::{Duration, Time};
+use time_tz::{timezones, Tz};
+
+use crate::{
+	action::Action,
+	burn_in::BurnIn,
+	draw::{Agenda, Measurements, MonthView},
+	events::Vacation,
+	night_mode::NightMode,
+	schedule::{
+		air_quality::AirQualityAlert, countdown::CountdownAlert, github_notifications::GithubNotifications, Reminder,
+		Schedule,
+	},
+	screensaver::{self, BearReminder},
+	storage::AIR_SENSOR,
+	sync::{Source, Sync},
+};
+
+/// Environment variable used to locate the configuration file if `--config` is not passed.
+pub const CONFIG_ENV: &str = "RASPI_OLED_CONFIG";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+	/// SQLite database containing the sensor readings.
+	#[serde(default = "default_database")]
+	pub database: PathBuf,
+	/// Events for the calendar view: the JSON written by `refresh_json`, an iCalendar file
+	/// or a directory of iCalendar files.
+	#[serde(default = "default_events")]
+	pub events: PathBuf,
+	/// Days off, weekly events are not shown during them.
+	#[serde(default, rename = "vacation")]
+	pub vacations: Vec<Vacation>,
+	/// IANA name of the time zone used for the clock and all schedules.
+	#[serde(default = "default_timezone")]
+	pub timezone: String,
+	/// Weekday names, date order and 12/24 hour clock: `de`, `en` or `fr`.
+	#[serde(default)]
+	pub locale: Locale,
+	/// IDs of the screensavers that can be activated.
+	#[serde(default = "default_screensavers")]
+	pub screensavers: Vec<String>,
+	#[serde(default = "default_schedules", rename = "schedule")]
+	pub schedules: Vec<ScheduleConfig>,
+	/// Address of the HTTP control API, disabled if unset.
+	#[serde(default)]
+	pub listen: Option<String>,
+	/// GPIO wiring, the `buttons` are up, down and select.
+	#[serde(default)]
+	pub hardware: HardwareProfile,
+	#[serde(default)]
+	pub brightness: BrightnessConfig,
+	#[serde(default)]
+	pub burn_in: BurnInConfig,
+	/// Calendar sources of `refresh_json`.
+	#[serde(default)]
+	pub sync: SyncConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncConfig {
+	/// Directory for the last response of each source.
+	#[serde(default = "default_sync_cache")]
+	pub cache: PathBuf,
+	#[serde(default = "default_sync_sources")]
+	pub sources: Vec<Source>,
+}
+
+/// Burn-in protection, see [`BurnInPolicy`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BurnInConfig {
+	/// Policy of the screens not listed in `screens`.
+	#[serde(default)]
+	pub default: BurnInPolicy,
+	/// Policies by screen name, as reported by the control API.
+	#[serde(default = "default_burn_in_screens")]
+	pub screens: HashMap<String, BurnInPolicy>,
+}
+
+/// Brightness schedule, see [`NightMode`].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrightnessConfig {
+	/// Brightness changes, the display stays at full brightness if empty.
+	#[serde(default)]
+	pub schedule: Vec<BrightnessLevel>,
+	/// Seconds the display stays on after a button press while it is off.
+	#[serde(default = "default_wake_secs")]
+	pub wake_secs: u64,
+	/// Brightness while woken up.
+	#[serde(default = "default_wake_level")]
+	pub wake_level: u8,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrightnessLevel {
+	/// Local time formatted as `HH:MM`.
+	pub time: String,
+	/// 0 (off) to 255 (full brightness).
+	pub level: u8,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ScheduleConfig {
+	/// Activate a screensaver at a fixed time every day.
+	Reminder {
+		/// Local time formatted as `HH:MM`.
+		time: String,
+		/// Screensaver ID.
+		action: String,
+		#[serde(default)]
+		beep: bool,
+	},
+	/// The teddy bear reminder on monday, wednesday and friday evenings.
+	Bear,
+	/// Poll GitHub for unread notifications once every minute.
+	GithubNotifications {
+		/// Environment variable containing the personal access token.
+		#[serde(default = "default_pat_env")]
+		pat_env: String,
+	},
+	/// Ask to open the window when the eCO2 level is too high.
+	AirQuality {
+		/// eCO2 in ppm that triggers the alert.
+		#[serde(default = "default_co2_threshold")]
+		threshold: u16,
+		/// The alert is repeated only after eCO2 dropped this far below the threshold.
+		#[serde(default = "default_co2_hysteresis")]
+		hysteresis: u16,
+		#[serde(default)]
+		beep: bool,
+		/// Sensor id the readings are stored under.
+		#[serde(default = "default_air_sensor")]
+		sensor: String,
+	},
+	/// Count down to the next event of `events`.
+	Countdown {
+		/// Minutes before the start, overridden by `alertMinutes` of the event.
+		#[serde(default = "default_countdown_minutes")]
+		minutes: u32,
+		#[serde(default)]
+		beep: bool,
+	},
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+	Io(PathBuf, std::io::Error),
+	Parse(PathBuf, toml::de::Error),
+	/// Semantically invalid value, `key` is the path of the offending key.
+	Invalid {
+		key: String,
+		message: String,
+	},
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ConfigError::Io(path, e) => write!(f, "failed to read {}: {e}", path.display()),
+			ConfigError::Parse(path, e) => write!(f, "failed to parse {}: {e}", path.display()),
+			ConfigError::Invalid { key, message } => write!(f, "invalid value for `{key}`: {message}"),
+		}
+	}
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(key: impl Into<String>, message: impl Into<String>) -> ConfigError {
+	ConfigError::Invalid {
+		key: key.into(),
+		message: message.into(),
+	}
+}
+
+fn default_database() -> PathBuf {
+	"sensors.db".into()
+}
+
+fn default_events() -> PathBuf {
+	"events.json".into()
+}
+
+fn default_timezone() -> String {
+	"Europe/Berlin".to_owned()
+}
+
+fn default_countdown_minutes() -> u32 {
+	10
+}
+
+fn default_pat_env() -> String {
+	"GITHUB_PAT".to_owned()
+}
+
+fn default_co2_threshold() -> u16 {
+	1500
+}
+
+fn default_co2_hysteresis() -> u16 {
+	300
+}
+
+fn default_air_sensor() -> String {
+	AIR_SENSOR.to_owned()
+}
+
+fn default_wake_secs() -> u64 {
+	30
+}
+
+fn default_wake_level() -> u8 {
+	64
+}
+
+impl Default for BrightnessConfig {
+	fn default() -> Self {
+		BrightnessConfig {
+			schedule: vec![],
+			wake_secs: default_wake_secs(),
+			wake_level: default_wake_level(),
+		}
+	}
+}
+
+/// The clock moves by itself.
+fn default_burn_in_screens() -> HashMap<String, BurnInPolicy> {
+	HashMap::from([("time".to_owned(), BurnInPolicy::OFF)])
+}
+
+impl Default for BurnInConfig {
+	fn default() -> Self {
+		BurnInConfig {
+			default: BurnInPolicy::default(),
+			screens: default_burn_in_screens(),
+		}
+	}
+}
+
+fn default_sync_cache() -> PathBuf {
+	"calendar-cache".into()
+}
+
+fn default_sync_sources() -> Vec<Source> {
+	vec![Source::Json {
+		url: "http://nixos.fritz.box:12783/custom/event_alerts".to_owned(),
+	}]
+}
+
+impl Default for SyncConfig {
+	fn default() -> Self {
+		SyncConfig {
+			cache: default_sync_cache(),
+			sources: default_sync_sources(),
+		}
+	}
+}
+
+fn default_screensavers() -> Vec<String> {
+	[
+		"star",
+		"rpi",
+		"duolingo",
+		"spaghetti",
+		"plate",
+		"measurements",
+		"measurements_temps",
+		"measurements_events",
+		"agenda",
+		"month",
+	]
+	.iter()
+	.map(|x| x.to_string())
+	.collect()
+}
+
+fn default_schedules() -> Vec<ScheduleConfig> {
+	let reminder = |time: &str, action: &str| ScheduleConfig::Reminder {
+		time: time.to_owned(),
+		action: action.to_owned(),
+		beep: false,
+	};
+	vec![
+		reminder("11:40", "duolingo"),
+		reminder("23:40", "duolingo"),
+		reminder("13:15", "plate"),
+		ScheduleConfig::GithubNotifications {
+			pat_env: default_pat_env(),
+		},
+		ScheduleConfig::Bear,
+	]
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			database: default_database(),
+			events: default_events(),
+			vacations: vec![],
+			timezone: default_timezone(),
+			locale: Locale::default(),
+			screensavers: default_screensavers(),
+			schedules: default_schedules(),
+			listen: None,
+			hardware: HardwareProfile::default(),
+			brightness: BrightnessConfig::default(),
+			burn_in: BurnInConfig::default(),
+			sync: SyncConfig::default(),
+		}
+	}
+}
+
+impl Config {
+	pub fn load(path: &Path) -> Result<Self, ConfigError> {
+		let text = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_owned(), e))?;
+		let config: Config = toml::from_str(&text).map_err(|e| ConfigError::Parse(path.to_owned(), e))?;
+		config.validate()?;
+		Ok(config)
+	}
+
+	/// Load the file passed via `--config <path>` or named by `$RASPI_OLED_CONFIG`.
+	/// Falls back to the default configuration if neither is set.
+	pub fn from_args(args: &[String]) -> Result<Self, ConfigError> {
+		let path = args
+			.iter()
+			.position(|x| x == "--config")
+			.map(|idx| {
+				args.get(idx + 1)
+					.map(PathBuf::from)
+					.ok_or_else(|| invalid("--config", "missing path"))
+			})
+			.transpose()?
+			.or_else(|| env::var_os(CONFIG_ENV).map(PathBuf::from));
+		match path {
+			Some(path) => Self::load(&path),
+			None => Ok(Self::default()),
+		}
+	}
+
+	/// Check everything that does not depend on the environment.
+	fn validate(&self) -> Result<(), ConfigError> {
+		self.timezone()?;
+		for (idx, id) in self.screensavers.iter().enumerate() {
+			if !SCREENSAVER_IDS.contains(&id.as_str()) {
+				return Err(invalid(
+					format!("screensavers[{idx}]"),
+					format!("unknown screensaver {id:?}"),
+				));
+			}
+		}
+		for (idx, vacation) in self.vacations.iter().enumerate() {
+			match vacation.dates() {
+				Some((start, end)) if start > end => {
+					return Err(invalid(format!("vacation[{idx}].end"), "must not be before `start`"));
+				},
+				Some(_) => {},
+				None => return Err(invalid(format!("vacation[{idx}]"), "expected dates like 2024-07-22")),
+			}
+		}
+		if self.hardware.buttons.len() != 3 {
+			return Err(invalid(
+				"hardware.buttons",
+				"expected three buttons: up, down and select",
+			));
+		}
+		for (idx, schedule) in self.schedules.iter().enumerate() {
+			match schedule {
+				ScheduleConfig::Reminder { time, action, .. } => {
+					parse_time(time).ok_or_else(|| invalid(format!("schedule[{idx}].time"), "expected HH:MM"))?;
+					if !self.screensavers.contains(action) {
+						return Err(invalid(
+							format!("schedule[{idx}].action"),
+							format!("{action:?} is not listed in `screensavers`"),
+						));
+					}
+				},
+				ScheduleConfig::AirQuality {
+					threshold, hysteresis, ..
+				} if hysteresis >= threshold => {
+					return Err(invalid(
+						format!("schedule[{idx}].hysteresis"),
+						"must be smaller than `threshold`",
+					));
+				},
+				_ => {},
+			}
+		}
+		Ok(())
+	}
+
+	pub fn night_mode(&self) -> Result<NightMode, ConfigError> {
+		let mut levels = vec![];
+		for (idx, entry) in self.brightness.schedule.iter().enumerate() {
+			let (hour, minute) = parse_time(&entry.time)
+				.ok_or_else(|| invalid(format!("brightness.schedule[{idx}].time"), "expected HH:MM"))?;
+			levels.push((Time::from_hms(hour, minute, 0).unwrap(), entry.level));
+		}
+		Ok(NightMode::new(
+			levels,
+			self.brightness.wake_level,
+			Duration::seconds(self.brightness.wake_secs as i64),
+		))
+	}
+
+	pub fn burn_in(&self) -> BurnIn {
+		BurnIn::new(self.burn_in.default, self.burn_in.screens.clone())
+	}
+
+	pub fn sync(&self) -> Sync {
+		Sync::new(self.sync.sources.clone(), self.sync.cache.clone())
+	}
+
+	pub fn timezone(&self) -> Result<&'static Tz, ConfigError> {
+		timezones::get_by_name(&self.timezone)
+			.ok_or_else(|| invalid("timezone", format!("unknown time zone {:?}", self.timezone)))
+	}
+
+	pub fn build_screensavers<D: DrawTarget<Color = Rgb565>>(
+		&self,
+	) -> Result<Vec<Box<dyn Screensaver<D>>>, ConfigError> {
+		self.screensavers
+			.iter()
+			.enumerate()
+			.map(|(idx, id)| {
+				screensaver_by_id(id)
+					.ok_or_else(|| invalid(format!("screensavers[{idx}]"), format!("unknown screensaver {id:?}")))
+			})
+			.collect()
+	}
+
+	/// Create the schedules. Reminder actions are resolved against `screensavers`.
+	pub fn build_schedules<D: DrawTarget<Color = Rgb565>>(
+		&self,
+		screensavers: &[Box<dyn Screensaver<D>>],
+	) -> Result<Vec<Box<dyn Schedule<D>>>, ConfigError> {
+		let mut scheduled: Vec<Box<dyn Schedule<D>>> = vec![];
+		for (idx, schedule) in self.schedules.iter().enumerate() {
+			match schedule {
+				ScheduleConfig::Reminder { time, action, beep } => {
+					let (hour, minute) =
+						parse_time(time).ok_or_else(|| invalid(format!("schedule[{idx}].time"), "expected HH:MM"))?;
+					let id = screensavers
+						.iter()
+						.map(|x| x.id())
+						.find(|x| x == action)
+						.ok_or_else(|| {
+							invalid(
+								format!("schedule[{idx}].action"),
+								format!("{action:?} is not listed in `screensavers`"),
+							)
+						})?;
+					scheduled.push(Box::new(Reminder::new(hour, minute, Action::Screensaver(id), *beep)));
+				},
+				ScheduleConfig::Bear => scheduled.push(Box::new(BearReminder::default())),
+				ScheduleConfig::GithubNotifications { pat_env } => {
+					let pat = env::var(pat_env).map_err(|_| {
+						invalid(
+							format!("schedule[{idx}].pat_env"),
+							format!("environment variable {pat_env} is not set"),
+						)
+					})?;
+					scheduled.push(Box::new(GithubNotifications {
+						pat,
+						last_modified: RefCell::new(None),
+						last_call: RefCell::new(None),
+					}));
+				},
+				ScheduleConfig::AirQuality {
+					threshold,
+					hysteresis,
+					beep,
+					sensor,
+				} => scheduled.push(Box::new(AirQualityAlert::new(
+					sensor.clone(),
+					*threshold,
+					*hysteresis,
+					*beep,
+				))),
+				ScheduleConfig::Countdown { minutes, beep } => scheduled.push(Box::new(CountdownAlert::new(
+					self.events.clone(),
+					self.vacations.clone(),
+					*minutes,
+					*beep,
+				))),
+			}
+		}
+		Ok(scheduled)
+	}
+}
+
+/// All screensavers that can be listed in `screensavers`.
+pub const SCREENSAVER_IDS: &[&str] = &[
+	"star",
+	"rpi",
+	"duolingo",
+	"spaghetti",
+	"plate",
+	"github",
+	"teddy_bear",
+	"measurements",
+	"measurements_temps",
+	"measurements_events",
+	"measurements_air",
+	"agenda",
+	"month",
+];
+
+fn screensaver_by_id<D: DrawTarget<Color = Rgb565>>(id: &str) -> Option<Box<dyn Screensaver<D>>> {
+	Some(match id {
+		"star" => Box::new(screensaver::STAR.clone()),
+		"rpi" => Box::new(screensaver::RPI.clone()),
+		"duolingo" => Box::new(screensaver::DUOLINGO.clone()),
+		"spaghetti" => Box::new(screensaver::SPAGHETTI.clone()),
+		"plate" => Box::new(screensaver::PLATE.clone()),
+		"github" => Box::new(screensaver::GITHUB.clone()),
+		"teddy_bear" => Box::new(screensaver::TEDDY_BEAR.clone()),
+		"measurements" => Box::new(Measurements::default()),
+		"measurements_temps" => Box::new(Measurements::temps()),
+		"measurements_events" => Box::new(Measurements::events()),
+		"measurements_air" => Box::new(Measurements::air_quality()),
+		"agenda" => Box::new(Agenda::default()),
+		"month" => Box::new(MonthView::default()),
+		_ => return None,
+	})
+}
+
+/// Parse `HH:MM` into hour and minute.
+fn parse_time(time: &str) -> Option<(u8, u8)> {
+	let (hour, minute) = time.split_once(':')?;
+	let hour: u8 = hour.parse().ok()?;
+	let minute: u8 = minute.parse().ok()?;
+	if hour >= 24 || minute >= 60 {
+		return None;
+	}
+	Some((hour, minute))
+}
+
+#[test]
+fn test_parse_config() {
+	let config: Config = toml::from_str(
+		r#"
+		database = "/var/lib/raspi-oled/sensors.db"
+		timezone = "America/New_York"
+		locale = "en"
+		screensavers = ["star", "plate"]
+
+		[[schedule]]
+		type = "reminder"
+		time = "12:30"
+		action = "plate"
+		beep = true
+
+		[[vacation]]
+		start = "2024-07-22"
+		end = "2024-08-30"
+
+		[[sync.sources]]
+		type = "caldav"
+		url = "https://dav.example.org/calendars/me/home/"
+		username = "me"
+		password_env = "CALDAV_PASSWORD"
+		"#,
+	)
+	.unwrap();
+	config.validate().unwrap();
+	assert_eq!(config.events, PathBuf::from("events.json"));
+	assert_eq!(config.locale, Locale::En);
+	assert_eq!(config.vacations.len(), 1);
+	assert_eq!(config.sync.cache, PathBuf::from("calendar-cache"));
+	assert!(matches!(
+		&config.sync.sources[..],
+		[Source::Caldav { username: Some(_), .. }]
+	));
+	let screensavers = config
+		.build_screensavers::<embedded_graphics::mock_display::MockDisplay<Rgb565>>()
+		.unwrap();
+	assert_eq!(screensavers.len(), 2);
+	assert_eq!(config.build_schedules(&screensavers).unwrap().len(), 1);
+}
+
+#[test]
+fn test_invalid_config() {
+	let config: Config = toml::from_str(
+		r#"
+		screensavers = ["star"]
+
+		[[schedule]]
+		type = "reminder"
+		time = "12:30"
+		action = "plate"
+		"#,
+	)
+	.unwrap();
+	let err = config.validate().unwrap_err().to_string();
+	assert!(err.contains("schedule[0].action"), "{}", err);
+
+	let err = toml::from_str::<Config>("timzone = \"UTC\"").unwrap_err().to_string();
+	assert!(err.contains("timzone"), "{}", err);
+}
@@ -1,19 +1,24 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{
+	cell::RefCell,
+	path::{Path, PathBuf},
+	rc::Rc,
+};
 
 use embedded_graphics::{pixelcolor::Rgb565, prelude::DrawTarget};
 use rand_xoshiro::Xoroshiro128StarStar;
-use raspi_lib::{Draw, Screensaver, TimeDisplay};
-use rusqlite::Connection;
+use raspi_lib::{Clock, Draw, Locale, Screensaver, SystemClock, TimeDisplay};
 use time::OffsetDateTime;
-use time_tz::{timezones::db::europe::BERLIN, OffsetDateTimeExt};
+use time_tz::{OffsetDateTimeExt, Tz};
 
 use crate::{
 	action::Action,
+	config::{Config, ConfigError},
 	disable_pwm,
-	draw::{self, Measurements, Totp},
+	draw::{Agenda, Measurements, MonthView, Totp},
 	enable_pwm,
-	schedule::{self, github_notifications::GithubNotifications, Schedule},
-	screensaver::{self, BearReminder},
+	events::{Events, Vacation},
+	schedule::Schedule,
+	storage::Storage,
 };
 
 pub static BLACK: Rgb565 = Rgb565::new(0, 0, 0);
@@ -27,9 +32,26 @@ pub trait Context<D: DrawTarget<Color = Rgb565>> {
 
 	fn active_count(&self) -> usize;
 
-	fn database(&self) -> Rc<RefCell<Connection>>;
+	fn database(&self) -> Rc<Storage>;
 
 	fn enable_pwm(&self);
+
+	/// Time zone used for clocks, calendars and schedules.
+	fn timezone(&self) -> &'static Tz;
+
+	/// Weekday names and date and time formats.
+	fn locale(&self) -> Locale;
+
+	/// Source of the current time. Never call [`OffsetDateTime::now_utc`] directly.
+	fn clock(&self) -> &dyn Clock;
+
+	/// Shared handle to the clock, for draws that need the time.
+	fn clock_handle(&self) -> Rc<dyn Clock>;
+
+	/// Current time in [`Context::timezone`].
+	fn now(&self) -> OffsetDateTime {
+		self.clock().now_utc().to_timezone(self.timezone())
+	}
 }
 
 pub trait DrawWithContext<D: DrawTarget<Color = Rgb565>>: Draw<D> {
@@ -42,29 +64,64 @@ pub struct ContextDefault<D: DrawTarget<Color = Rgb565>> {
 	screensavers: Vec<Box<dyn Screensaver<D>>>,
 	scheduled: Vec<Box<dyn Schedule<D>>>,
 	pub active: RefCell<Vec<Box<dyn Draw<D>>>>,
-	database: Rc<RefCell<Connection>>,
+	database: Rc<Storage>,
+	events: PathBuf,
+	vacations: Vec<Vacation>,
+	timezone: &'static Tz,
+	locale: Locale,
+	clock: Rc<dyn Clock>,
 }
 
 impl<D: DrawTarget<Color = Rgb565>> ContextDefault<D> {
-	pub fn new() -> Self {
-		let mut screensavers = screensaver::screensavers();
-		screensavers.push(Box::new(draw::Measurements::default()));
-		screensavers.push(Box::new(draw::Measurements::temps()));
-		screensavers.push(Box::new(draw::Measurements::events()));
-		let database = Connection::open("sensors.db").expect("failed to open database");
-		let mut scheduled = schedule::reminders();
-		scheduled.push(Box::new(GithubNotifications {
-			pat: std::env::var("GITHUB_PAT").expect("no env var GITHUB_PAT set"),
-			last_modified: RefCell::new(None),
-			last_call: RefCell::new(OffsetDateTime::now_utc().to_timezone(BERLIN) - time::Duration::seconds(50)),
-		}));
-		scheduled.push(Box::new(BearReminder::default()));
-		ContextDefault {
-			database: Rc::new(RefCell::new(database)),
+	pub fn new(config: &Config) -> Result<Self, ConfigError> {
+		Self::with_clock(config, Rc::new(SystemClock))
+	}
+
+	/// Like [`ContextDefault::new`], but reads the current time from `clock`.
+	pub fn with_clock(config: &Config, clock: Rc<dyn Clock>) -> Result<Self, ConfigError> {
+		let screensavers = config.build_screensavers()?;
+		let scheduled = config.build_schedules(&screensavers)?;
+		let database = Storage::open(&config.database).map_err(|e| ConfigError::Invalid {
+			key: "database".to_owned(),
+			message: e.to_string(),
+		})?;
+		let timezone = config.timezone()?;
+		Ok(ContextDefault {
+			database: Rc::new(database),
 			screensavers,
 			scheduled,
-			active: RefCell::new(vec![Box::new(TimeDisplay::new())]),
-		}
+			active: RefCell::new(vec![Box::new(TimeDisplay::new(timezone, clock.clone(), config.locale))]),
+			events: config.events.clone(),
+			vacations: config.vacations.clone(),
+			timezone,
+			locale: config.locale,
+			clock,
+		})
+	}
+
+	/// Path of the event JSON data file.
+	pub fn events_path(&self) -> &Path {
+		&self.events
+	}
+
+	/// Load the events, recurring iCalendar events are expanded from `from`.
+	/// Errors are logged and result in no events.
+	pub fn load_events(&self, from: OffsetDateTime) -> Events {
+		let mut events = Events::load(&self.events, self.timezone, from).unwrap_or_else(|e| {
+			eprintln!("error: {}", e);
+			Events::default()
+		});
+		events.vacations = self.vacations.clone();
+		events
+	}
+
+	pub fn screensaver_ids(&self) -> impl Iterator<Item = &'static str> + '_ {
+		self.screensavers.iter().map(|x| x.id())
+	}
+
+	/// Look up a configured screensaver.
+	pub fn screensaver_id(&self, id: &str) -> Option<&'static str> {
+		self.screensaver_ids().find(|x| *x == id)
 	}
 
 	pub fn add(&mut self, totp: Totp) {
@@ -72,7 +129,7 @@ impl<D: DrawTarget<Color = Rgb565>> ContextDefault<D> {
 	}
 
 	pub fn loop_iter(&mut self, disp: &mut D, rng: &mut Rng) -> bool {
-		let time = OffsetDateTime::now_utc().to_timezone(BERLIN);
+		let time = self.now();
 		// check schedules
 		for s in &self.scheduled {
 			s.check_and_do(&*self, time);
@@ -84,23 +141,51 @@ impl<D: DrawTarget<Color = Rgb565>> ContextDefault<D> {
 		let a = active.last().unwrap();
 		if !a.expired() {
 			let measure: Option<&Measurements> = a.as_any().downcast_ref();
+			let agenda: Option<&Agenda> = a.as_any().downcast_ref();
+			let month: Option<&MonthView> = a.as_any().downcast_ref();
 			if let Some(measure) = measure {
 				return measure.draw_with_ctx(self, disp, rng).unwrap_or(true);
+			} else if let Some(agenda) = agenda {
+				return agenda.draw_with_ctx(self, disp, rng).unwrap_or(true);
+			} else if let Some(month) = month {
+				return month.draw_with_ctx(self, disp, rng).unwrap_or(true);
 			} else {
 				return a.draw(disp, rng).unwrap_or(true);
 			}
 		}
 		drop(active);
-		self.active.borrow_mut().pop();
+		self.active.get_mut().pop();
 		disable_pwm().unwrap();
-		self.loop_iter(disp, rng)
+		// the screen below does not know it was covered
+		let _ = self.redraw(disp);
+		self.loop_iter(disp, rng);
+		true
+	}
+
+	/// Put `drawable` on top of the active draws with the same or a lower priority.
+	fn push(&self, drawable: Box<dyn Draw<D>>) {
+		let mut active = self.active.borrow_mut();
+		let idx = active
+			.iter()
+			.rposition(|x| x.priority() <= drawable.priority())
+			.map_or(0, |idx| idx + 1);
+		active.insert(idx, drawable);
+	}
+
+	/// Clear the display and draw the active screen again on the next frame.
+	pub fn redraw(&mut self, disp: &mut D) -> Result<(), D::Error> {
+		disp.clear(BLACK)?;
+		if let Some(x) = self.active.get_mut().last_mut() {
+			x.redraw();
+		}
+		Ok(())
 	}
 
 	pub fn pop_action_and_clear(&mut self, disp: &mut D) -> Result<(), D::Error> {
 		let active = self.active.get_mut();
 		if active.len() > 1 {
 			active.pop();
-			disp.clear(BLACK)?;
+			return self.redraw(disp);
 		}
 		Ok(())
 	}
@@ -108,7 +193,7 @@ impl<D: DrawTarget<Color = Rgb565>> ContextDefault<D> {
 
 impl<D: DrawTarget<Color = Rgb565>> Context<D> for ContextDefault<D> {
 	fn do_draw(&self, drawable: Box<dyn Draw<D>>) {
-		self.active.borrow_mut().push(drawable);
+		self.push(drawable);
 	}
 
 	fn do_action(&self, action: Action) {
@@ -116,7 +201,7 @@ impl<D: DrawTarget<Color = Rgb565>> Context<D> for ContextDefault<D> {
 			Action::Screensaver(id) => {
 				for s in &self.screensavers {
 					if s.id() == id {
-						self.active.borrow_mut().push(s.convert_draw());
+						self.push(s.convert_draw());
 						return;
 					}
 				}
@@ -129,11 +214,27 @@ impl<D: DrawTarget<Color = Rgb565>> Context<D> for ContextDefault<D> {
 		self.active.borrow().len()
 	}
 
-	fn database(&self) -> Rc<RefCell<Connection>> {
+	fn database(&self) -> Rc<Storage> {
 		self.database.clone()
 	}
 
 	fn enable_pwm(&self) {
 		enable_pwm().unwrap();
 	}
+
+	fn timezone(&self) -> &'static Tz {
+		self.timezone
+	}
+
+	fn locale(&self) -> Locale {
+		self.locale
+	}
+
+	fn clock(&self) -> &dyn Clock {
+		&*self.clock
+	}
+
+	fn clock_handle(&self) -> Rc<dyn Clock> {
+		self.clock.clone()
+	}
 }
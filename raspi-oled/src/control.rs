@@ -0,0 +1,290 @@
+//! Minimal HTTP API to control a running `main_loop`.
+//!
+//! | Route                       | Effect                                      |
+//! |-----------------------------|---------------------------------------------|
+//! | `GET /active`               | names of the active draws, bottom to top    |
+//! | `POST /screensaver/<id>`    | show a configured screensaver               |
+//! | `POST /text`                | show the request body as text               |
+//! | `POST /beep/<seconds>`      | beep for the given number of seconds        |
+//! | `POST /pop`                 | close the topmost screen                    |
+//!
+//! Requests are parsed on a separate thread and executed by [`ControlServer::process`],
+//! which has to be called by the main loop once per frame.
+
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{SocketAddr, TcpListener, TcpStream},
+	sync::mpsc::{self, Receiver, Sender},
+	thread,
+	time::Duration,
+};
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::DrawTarget};
+use raspi_lib::TextNotification;
+use time::OffsetDateTime;
+
+use crate::{
+	action::Action,
+	context::{Context, ContextDefault},
+	disable_pwm,
+};
+
+/// Longest accepted request body in bytes.
+const MAX_BODY: usize = 4096;
+/// Longest accepted beep in seconds.
+const MAX_BEEP: u64 = 60;
+/// Frames until a text message disappears (about ten seconds).
+const MESSAGE_FRAMES: u32 = 150;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+	Active,
+	Screensaver(String),
+	Text(String),
+	Beep(u64),
+	Pop,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+	pub status: u16,
+	pub body: String,
+}
+
+impl Response {
+	fn ok(body: impl Into<String>) -> Self {
+		Response {
+			status: 200,
+			body: body.into(),
+		}
+	}
+
+	fn error(status: u16, body: impl Into<String>) -> Self {
+		Response {
+			status,
+			body: body.into(),
+		}
+	}
+}
+
+pub struct ControlServer {
+	addr: SocketAddr,
+	requests: Receiver<(Command, Sender<Response>)>,
+	beep_until: Option<OffsetDateTime>,
+}
+
+impl ControlServer {
+	/// Listen on `addr` (e.g. `0.0.0.0:8123`).
+	pub fn spawn(addr: &str) -> io::Result<Self> {
+		let listener = TcpListener::bind(addr)?;
+		let addr = listener.local_addr()?;
+		let (tx, rx) = mpsc::channel();
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				let Ok(stream) = stream else {
+					continue;
+				};
+				if let Err(e) = handle_connection(stream, &tx) {
+					eprintln!("control: {e}");
+				}
+			}
+		});
+		Ok(ControlServer {
+			addr,
+			requests: rx,
+			beep_until: None,
+		})
+	}
+
+	pub fn local_addr(&self) -> SocketAddr {
+		self.addr
+	}
+
+	/// Execute pending commands. Must be called regularly by the thread owning the context.
+	pub fn process<D: DrawTarget<Color = Rgb565>>(&mut self, ctx: &mut ContextDefault<D>, disp: &mut D) {
+		if let Some(until) = self.beep_until {
+			if ctx.now() >= until {
+				let _ = disable_pwm();
+				self.beep_until = None;
+			}
+		}
+		while let Ok((command, reply)) = self.requests.try_recv() {
+			let _ = reply.send(self.execute(ctx, disp, command));
+		}
+	}
+
+	fn execute<D: DrawTarget<Color = Rgb565>>(
+		&mut self,
+		ctx: &mut ContextDefault<D>,
+		disp: &mut D,
+		command: Command,
+	) -> Response {
+		match command {
+			Command::Active => {
+				let names: Vec<_> = ctx.active.borrow().iter().map(|x| x.name()).collect();
+				Response::ok(names.join("\n"))
+			},
+			Command::Screensaver(id) => match ctx.screensaver_id(&id) {
+				Some(id) => {
+					ctx.do_action(Action::Screensaver(id));
+					Response::ok("ok")
+				},
+				None => Response::error(404, format!("unknown screensaver {id:?}")),
+			},
+			Command::Text(text) => {
+				ctx.do_draw(Box::new(TextNotification::new(text).with_timeout(MESSAGE_FRAMES)));
+				Response::ok("ok")
+			},
+			Command::Beep(seconds) => {
+				ctx.enable_pwm();
+				self.beep_until = Some(ctx.now() + time::Duration::seconds(seconds as i64));
+				Response::ok("ok")
+			},
+			Command::Pop => {
+				if ctx.active_count() <= 1 {
+					return Response::error(409, "nothing to close");
+				}
+				let _ = ctx.pop_action_and_clear(disp);
+				let _ = disable_pwm();
+				self.beep_until = None;
+				Response::ok("ok")
+			},
+		}
+	}
+}
+
+/// Map an HTTP request to a command.
+pub fn parse_request(method: &str, path: &str, body: &str) -> Result<Command, Response> {
+	let path = path.split('?').next().unwrap_or_default();
+	let parts: Vec<_> = path.split('/').filter(|x| !x.is_empty()).collect();
+	let command = match (method, &*parts) {
+		("GET", ["active"]) => Command::Active,
+		("POST", ["screensaver", id]) => Command::Screensaver(id.to_string()),
+		("POST", ["text"]) => {
+			if body.trim().is_empty() {
+				return Err(Response::error(400, "empty text"));
+			}
+			Command::Text(body.to_owned())
+		},
+		("POST", ["beep", seconds]) => match seconds.parse() {
+			Ok(seconds) if seconds <= MAX_BEEP => Command::Beep(seconds),
+			_ => return Err(Response::error(400, format!("expected 0 to {MAX_BEEP} seconds"))),
+		},
+		("POST", ["pop"]) => Command::Pop,
+		(_, ["active"]) | (_, ["screensaver", _]) | (_, ["text"]) | (_, ["beep", _]) | (_, ["pop"]) => {
+			return Err(Response::error(405, "method not allowed"))
+		},
+		_ => return Err(Response::error(404, "not found")),
+	};
+	Ok(command)
+}
+
+fn handle_connection(stream: TcpStream, requests: &Sender<(Command, Sender<Response>)>) -> io::Result<()> {
+	stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+	let mut reader = BufReader::new(stream.try_clone()?);
+	let mut request_line = String::new();
+	reader.read_line(&mut request_line)?;
+	let mut request = request_line.split_whitespace();
+	let (method, path) = (request.next().unwrap_or_default(), request.next().unwrap_or_default());
+
+	let mut content_length = 0;
+	loop {
+		let mut header = String::new();
+		if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+			break;
+		}
+		if let Some((key, value)) = header.split_once(':') {
+			if key.eq_ignore_ascii_case("content-length") {
+				content_length = value.trim().parse().unwrap_or(0);
+			}
+		}
+	}
+	let response = if content_length > MAX_BODY {
+		Response::error(413, "body too large")
+	} else {
+		let mut body = vec![0; content_length];
+		reader.read_exact(&mut body)?;
+		let body = String::from_utf8_lossy(&body);
+		match parse_request(method, path, &body) {
+			Ok(command) => {
+				let (tx, rx) = mpsc::channel();
+				if requests.send((command, tx)).is_err() {
+					Response::error(503, "shutting down")
+				} else {
+					rx.recv_timeout(Duration::from_secs(5))
+						.unwrap_or_else(|_| Response::error(503, "main loop not responding"))
+				}
+			},
+			Err(response) => response,
+		}
+	};
+	write_response(stream, &response)
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> io::Result<()> {
+	let reason = match response.status {
+		200 => "OK",
+		400 => "Bad Request",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		409 => "Conflict",
+		413 => "Payload Too Large",
+		_ => "Service Unavailable",
+	};
+	write!(
+		stream,
+		"HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}\n",
+		response.status,
+		reason,
+		response.body.len() + 1,
+		response.body
+	)?;
+	stream.flush()
+}
+
+#[test]
+fn test_parse_request() {
+	assert_eq!(parse_request("GET", "/active", ""), Ok(Command::Active));
+	assert_eq!(
+		parse_request("POST", "/screensaver/star", ""),
+		Ok(Command::Screensaver("star".to_owned()))
+	);
+	assert_eq!(parse_request("POST", "/beep/3?x=1", ""), Ok(Command::Beep(3)));
+	assert_eq!(parse_request("POST", "/beep/3600", "").unwrap_err().status, 400);
+	assert_eq!(parse_request("POST", "/text", " ").unwrap_err().status, 400);
+	assert_eq!(parse_request("GET", "/pop", "").unwrap_err().status, 405);
+	assert_eq!(parse_request("GET", "/", "").unwrap_err().status, 404);
+}
+
+#[test]
+fn test_control_server() {
+	use crate::{config::Config, headless::Headless};
+
+	let config: Config = toml::from_str("database = \":memory:\"\nscreensavers = [\"star\"]\nschedule = []").unwrap();
+	let mut headless = Headless::new(&config, OffsetDateTime::UNIX_EPOCH, 1).unwrap();
+	let mut server = ControlServer::spawn("127.0.0.1:0").unwrap();
+	let addr = server.local_addr();
+	let client = thread::spawn(move || {
+		let request = |request: &str| {
+			let mut stream = TcpStream::connect(addr).unwrap();
+			stream.write_all(request.as_bytes()).unwrap();
+			let mut response = String::new();
+			stream.read_to_string(&mut response).unwrap();
+			response
+		};
+		let star = request("POST /screensaver/star HTTP/1.1\r\nHost: x\r\n\r\n");
+		let unknown = request("POST /screensaver/rpi HTTP/1.1\r\n\r\n");
+		let text = request("POST /text HTTP/1.1\r\nContent-Length: 5\r\n\r\nHallo");
+		let active = request("GET /active HTTP/1.1\r\n\r\n");
+		(star, unknown, text, active)
+	});
+	while !client.is_finished() {
+		server.process(&mut headless.ctx, &mut headless.disp);
+		thread::sleep(Duration::from_millis(1));
+	}
+	let (star, unknown, text, active) = client.join().unwrap();
+	assert!(star.starts_with("HTTP/1.1 200"), "{}", star);
+	assert!(unknown.starts_with("HTTP/1.1 404"), "{}", unknown);
+	assert!(text.starts_with("HTTP/1.1 200"), "{}", text);
+	assert!(active.ends_with("\r\n\r\ntime\nstar\ntext\n"), "{}", active);
+}
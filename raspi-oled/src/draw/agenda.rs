@@ -0,0 +1,263 @@
+use std::{
+	any::Any,
+	cell::{Cell, RefCell},
+};
+
+use embedded_graphics::{
+	mono_font::{iso_8859_1::FONT_6X9, MonoTextStyle},
+	pixelcolor::Rgb565,
+	prelude::*,
+	primitives::Rectangle,
+	text::{Baseline, Text},
+	Drawable,
+};
+use raspi_lib::{Draw, Locale, Screensaver};
+use time::{OffsetDateTime, Time};
+use time_tz::{OffsetDateTimeExt, Tz};
+
+use crate::{
+	context::{Context, ContextDefault, DrawWithContext, Rng, BLACK},
+	events::{Occurrence, UPCOMING_DAYS},
+};
+
+/// Height of a line in pixels.
+const LINE_HEIGHT: i32 = 10;
+/// Lines per page.
+const LINES: usize = 12;
+/// Characters per line, between the marker and the scroll bar.
+const WIDTH: usize = 20;
+
+const HEADING: Rgb565 = Rgb565::new(0x0f, 0x1f, 0x1f);
+const TODAY: Rgb565 = Rgb565::YELLOW;
+const TIMES: Rgb565 = Rgb565::new(0x14, 0x28, 0x14);
+const ONGOING: Rgb565 = Rgb565::GREEN;
+
+/// List of all upcoming events, grouped by day and split into pages.
+/// Up and down turn the pages, see [`menu::press`](crate::menu::press).
+#[derive(Debug, Default)]
+pub struct Agenda {
+	/// Clamped to the available pages when drawing.
+	page: usize,
+	pages: RefCell<Vec<Vec<Line>>>,
+	/// Time the events were loaded, they are loaded again every minute.
+	loaded: Cell<Option<OffsetDateTime>>,
+	/// Marquee position of long event names.
+	step: Cell<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+	Day { text: String, today: bool },
+	Times { text: String, ongoing: bool },
+	Name { text: String, ongoing: bool },
+}
+
+impl Agenda {
+	pub fn next_page(&mut self) {
+		self.page = (self.page + 1).min(self.pages.get_mut().len().saturating_sub(1));
+		self.step.set(0);
+	}
+
+	pub fn previous_page(&mut self) {
+		self.page = self.page.saturating_sub(1);
+		self.step.set(0);
+	}
+
+	fn load<D: DrawTarget<Color = Rgb565>>(&self, ctx: &ContextDefault<D>, now: OffsetDateTime) {
+		let tz = ctx.timezone();
+		let events = ctx.load_events(now);
+		let upcoming = events.upcoming(now, UPCOMING_DAYS, tz);
+		for e in &upcoming.errors {
+			eprintln!("warning: skipping {}", e);
+		}
+		*self.pages.borrow_mut() = pages(&upcoming.occurrences, now, tz, ctx.locale());
+		self.loaded.set(Some(now));
+		self.step.set(0);
+	}
+}
+
+/// Split the occurrences into pages, repeating the day heading on pages that continue a day.
+fn pages(occurrences: &[Occurrence], now: OffsetDateTime, tz: &Tz, locale: Locale) -> Vec<Vec<Line>> {
+	let today = now.to_timezone(tz).date();
+	let mut pages: Vec<Vec<Line>> = vec![];
+	let mut day = None;
+	for occurrence in occurrences {
+		// events that started on an earlier day are listed under today
+		let date = occurrence.start.to_timezone(tz).date().max(today);
+		let new_day = day != Some(date);
+		day = Some(date);
+		if pages.last().is_none_or(|x| x.len() + 2 + new_day as usize > LINES) {
+			pages.push(vec![]);
+		}
+		let page = pages.last_mut().unwrap();
+		if new_day || page.is_empty() {
+			page.push(Line::Day {
+				text: format!("{} {}", locale.weekday(date.weekday()), locale.date(date)),
+				today: date == today,
+			});
+		}
+		let ongoing = occurrence.start <= now;
+		page.push(Line::Times {
+			text: times(occurrence, tz, locale),
+			ongoing,
+		});
+		page.push(Line::Name {
+			text: occurrence.name.clone(),
+			ongoing,
+		});
+	}
+	pages
+}
+
+fn times(occurrence: &Occurrence, tz: &Tz, locale: Locale) -> String {
+	let start = occurrence.start.to_timezone(tz);
+	let end = occurrence.end.to_timezone(tz);
+	if start.time() == Time::MIDNIGHT && end.time() == Time::MIDNIGHT && end > start {
+		let last = end.date().previous_day().unwrap_or(end.date());
+		return locale.all_day((last != start.date()).then_some(last));
+	}
+	let (from, to) = (locale.time(start.time()), locale.time(end.time()));
+	if end.date() == start.date() {
+		format!("{}-{}", from, to)
+	} else {
+		format!("{}-{} {}", from, locale.date(end.date()), to)
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Screensaver<D> for Agenda {
+	fn id(&self) -> &'static str {
+		"agenda"
+	}
+
+	fn convert_draw(&self) -> Box<dyn Draw<D>> {
+		Box::new(Agenda::default())
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> DrawWithContext<D> for Agenda {
+	fn draw_with_ctx(&self, ctx: &ContextDefault<D>, disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
+		let now = ctx.now();
+		let stale = self
+			.loaded
+			.get()
+			.is_none_or(|x| x.unix_timestamp() / 60 != now.unix_timestamp() / 60);
+		if stale {
+			self.load(ctx, now);
+		}
+		let pages = self.pages.borrow();
+		let page_idx = self.page.min(pages.len().saturating_sub(1));
+		let page = pages.get(page_idx).map_or(&[][..], |x| &x[..]);
+		let step = self.step.get();
+		self.step.set(step + 1);
+		let scrolls = page
+			.iter()
+			.any(|x| matches!(x, Line::Name { text, .. } if text.chars().count() > WIDTH));
+		if step > 0 && !scrolls {
+			return Ok(false);
+		}
+		disp.clear(BLACK)?;
+		if page.is_empty() {
+			let style = MonoTextStyle::new(&FONT_6X9, HEADING);
+			Text::with_baseline("No upcoming events", Point::new(4, 0), style, Baseline::Top).draw(disp)?;
+			return Ok(true);
+		}
+		for (idx, line) in page.iter().enumerate() {
+			let y = idx as i32 * LINE_HEIGHT;
+			let (text, color) = match line {
+				Line::Day { text, today } => (text.clone(), if *today { TODAY } else { HEADING }),
+				Line::Times { text, ongoing } => (text.clone(), if *ongoing { ONGOING } else { TIMES }),
+				Line::Name { text, .. } => (raspi_lib::marquee(text, WIDTH, step), Rgb565::WHITE),
+			};
+			let x = match line {
+				Line::Day { .. } => 0,
+				Line::Times { ongoing: true, .. } | Line::Name { ongoing: true, .. } => {
+					disp.fill_solid(
+						&Rectangle::new(Point::new(0, y), Size::new(2, LINE_HEIGHT as u32)),
+						ONGOING,
+					)?;
+					4
+				},
+				_ => 4,
+			};
+			let style = MonoTextStyle::new(&FONT_6X9, color);
+			Text::with_baseline(&text, Point::new(x, y), style, Baseline::Top).draw(disp)?;
+		}
+		if pages.len() > 1 {
+			let height = 128 / pages.len() as i32;
+			let bar = Rectangle::new(Point::new(126, page_idx as i32 * height), Size::new(2, height as u32));
+			disp.fill_solid(&bar, HEADING)?;
+		}
+		Ok(true)
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Draw<D> for Agenda {
+	fn draw(&self, _disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
+		panic!("draw without ctx");
+	}
+
+	fn redraw(&mut self) {
+		self.step.set(0);
+		self.loaded.set(None);
+	}
+
+	fn name(&self) -> &'static str {
+		"agenda"
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+#[test]
+fn test_agenda() {
+	use time::{Date, Duration};
+
+	let tz = raspi_lib::DEFAULT_TIMEZONE;
+	let friday = Date::from_calendar_date(2024, time::Month::May, 3).unwrap();
+	let at = |days, hour| raspi_lib::local_time(friday + Duration::days(days), Time::from_hms(hour, 0, 0).unwrap(), tz);
+	let occurrence = |name: &str, start, end| Occurrence {
+		name: name.to_owned(),
+		start,
+		end,
+		alert: None,
+	};
+	let mut occurrences = vec![
+		occurrence("Night shift", at(-1, 22), at(0, 6)),
+		occurrence("Holiday", at(1, 0), at(2, 0)),
+	];
+	occurrences.extend((0..5).map(|x| occurrence("Lecture on a very long topic", at(2, 8 + x), at(2, 9 + x))));
+	let pages = pages(&occurrences, at(0, 5), tz, Locale::En);
+	let text = |line: &Line| match line {
+		Line::Day { text, .. } | Line::Times { text, .. } | Line::Name { text, .. } => text.clone(),
+	};
+	let first: Vec<_> = pages[0].iter().map(text).collect();
+	assert_eq!(
+		first,
+		[
+			"Fr 5/3",
+			"10:00pm-5/3 6:00am",
+			"Night shift",
+			"Sa 5/4",
+			"all day",
+			"Holiday",
+			"Su 5/5",
+			"8:00am-9:00am",
+			"Lecture on a very long topic",
+			"9:00am-10:00am",
+			"Lecture on a very long topic",
+		]
+	);
+	assert!(matches!(&pages[0][1], Line::Times { ongoing: true, .. }));
+	assert!(matches!(&pages[0][4], Line::Times { ongoing: false, .. }));
+	// the heading is repeated on the next page
+	assert_eq!(pages.len(), 2);
+	assert_eq!(text(&pages[1][0]), "Su 5/5");
+	assert_eq!(times(&occurrences[1], tz, Locale::De), "ganztägig");
+	assert_eq!(pages[1].len(), 7);
+}
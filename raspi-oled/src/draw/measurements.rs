@@ -1,11 +1,11 @@
-use std::{any::Any, fs, ops::Sub, sync::atomic::AtomicBool, time::Duration};
+use std::{any::Any, sync::atomic::AtomicBool};
 
 use embedded_graphics::{
 	image::ImageRaw,
 	mono_font::{
 		ascii::{FONT_4X6, FONT_5X8, FONT_6X9, FONT_9X15},
 		mapping::StrGlyphMapping,
-		DecorationDimensions, MonoFont, MonoTextStyleBuilder,
+		DecorationDimensions, MonoFont, MonoTextStyle, MonoTextStyleBuilder,
 	},
 	pixelcolor::Rgb565,
 	prelude::*,
@@ -14,13 +14,14 @@ use embedded_graphics::{
 	Drawable,
 };
 use raspi_lib::{Draw, Screensaver};
-use time::{format_description, Date, OffsetDateTime, PrimitiveDateTime};
+use time::{Time, Weekday};
+use time_tz::OffsetDateTimeExt;
 
 use crate::{
 	context::{Context, ContextDefault, DrawWithContext, Rng, BLACK},
-	Events,
+	events::UPCOMING_DAYS,
+	storage::{Measurement, Quantity, AIR_SENSOR, DEFAULT_SENSOR},
 };
-use time_tz::{timezones::db::europe::BERLIN, OffsetDateTimeExt, PrimitiveDateTimeExt};
 
 static CLOCK_FONT: MonoFont = MonoFont {
 	image: ImageRaw::new(include_bytes!("font_15x30.raw"), 165),
@@ -32,6 +33,9 @@ static CLOCK_FONT: MonoFont = MonoFont {
 	strikethrough: DecorationDimensions::default_strikethrough(30),
 };
 
+/// eCO2 readings shown in the graph: 24 hours at one reading per minute.
+const AIR_READINGS: usize = 24 * 60;
+
 #[derive(Debug)]
 pub struct Measurements {
 	drawn: AtomicBool,
@@ -43,6 +47,8 @@ enum MeasurementsMode {
 	Default,
 	Temps,
 	Events,
+	/// eCO2/TVOC readout and eCO2 graph of the last 24 hours.
+	AirQuality,
 }
 
 impl Default for Measurements {
@@ -68,6 +74,13 @@ impl Measurements {
 			mode: MeasurementsMode::Events,
 		}
 	}
+
+	pub fn air_quality() -> Self {
+		Self {
+			drawn: AtomicBool::new(false),
+			mode: MeasurementsMode::AirQuality,
+		}
+	}
 }
 
 impl<D: DrawTarget<Color = Rgb565>> Screensaver<D> for Measurements {
@@ -76,6 +89,7 @@ impl<D: DrawTarget<Color = Rgb565>> Screensaver<D> for Measurements {
 			MeasurementsMode::Default => "measurements",
 			MeasurementsMode::Temps => "measurements_temps",
 			MeasurementsMode::Events => "measurements_events",
+			MeasurementsMode::AirQuality => "measurements_air",
 		}
 	}
 
@@ -93,30 +107,27 @@ impl<D: DrawTarget<Color = Rgb565>> DrawWithContext<D> for Measurements {
 			return Ok(false);
 		}
 		disp.clear(BLACK)?;
-		let events = fs::read_to_string("events.json").expect("failed to read events.json");
-		let events: Events = serde_json::from_str(&events).unwrap();
-		let database = ctx.database();
-		let database = database.borrow_mut();
-
-		let (rh, temp): (i64, i64) = database
-			.query_row(
-				"SELECT humidity, celsius FROM sensor_readings ORDER BY sensor_readings.time DESC LIMIT 1",
-				[],
-				|row| Ok((row.get(0).unwrap(), row.get(1).unwrap())),
-			)
-			.unwrap();
+		let events = ctx.load_events(ctx.now());
+		let storage = ctx.database();
+		let latest = |quantity| match storage.latest(DEFAULT_SENSOR, quantity) {
+			Ok(x) => x.map(|x| x.tenths()).unwrap_or_default(),
+			Err(e) => {
+				eprintln!("error: {}", e);
+				0
+			},
+		};
+		let (rh, temp) = (latest(Quantity::Humidity), latest(Quantity::Temperature));
 
-		let time = OffsetDateTime::now_utc().to_timezone(BERLIN);
+		let tz = ctx.timezone();
+		let time = ctx.now();
 
-		let mut query = database
-			.prepare("SELECT celsius FROM sensor_readings ORDER BY sensor_readings.time DESC LIMIT 288")
-			.unwrap();
-		let mut temps: Vec<i32> = query
-			.query_map([], |r| Ok(r.get(0)))
-			.unwrap()
-			.map(Result::unwrap)
-			.map(Result::unwrap)
-			.collect();
+		let mut temps: Vec<i32> = match storage.recent(DEFAULT_SENSOR, Quantity::Temperature, 288) {
+			Ok(x) => x.iter().map(Measurement::tenths).collect(),
+			Err(e) => {
+				eprintln!("error: {}", e);
+				vec![]
+			},
+		};
 		let mut global_min = 1000;
 		let mut global_max = 0;
 		let mut vals: Vec<(i32, i32)> = vec![];
@@ -224,72 +235,29 @@ impl<D: DrawTarget<Color = Rgb565>> DrawWithContext<D> for Measurements {
 		Rectangle::new((x + 1, y + 28).into(), (1, 1).into())
 			.into_styled(rect_style)
 			.draw(disp)?;
-		let mut day = time.weekday().number_days_from_monday() as usize;
-		let days = [
-			("M", "o"),
-			("D", "i"),
-			("M", "i"),
-			("D", "o"),
-			("F", "r"),
-			("S", "a"),
-			("S", "o"),
-		];
+		let locale = ctx.locale();
+		// first letter of the weekday large, the second one small
+		let weekday = |day: Weekday| locale.weekday(day).split_at(1);
+		let mut day = time.weekday();
 		for i in 0..5 {
-			Text::new(days[day].0, (x + 12 * i + 4, y + 6).into(), text_style_6x9).draw(disp)?;
-			Text::new(days[day].1, (x + 12 * i + 10, y + 6).into(), text_style4).draw(disp)?;
-			day += 1;
-			day %= days.len();
+			let (first, second) = weekday(day);
+			Text::new(first, (x + 12 * i + 4, y + 6).into(), text_style_6x9).draw(disp)?;
+			Text::new(second, (x + 12 * i + 10, y + 6).into(), text_style4).draw(disp)?;
+			day = day.next();
 		}
 		let mut bits = vec![];
 		// events
-		let mut all_events = vec![];
-		for event in events.weekly {
-			let mut event_time = time.clone();
-			while event_time.weekday().number_days_from_monday() as i32 != event.day {
-				event_time += Duration::from_secs(24 * 60 * 60);
-			}
-			all_events.push((
-				event.day,
-				event.hour,
-				event.minute,
-				event.duration,
-				event.name,
-				event_time.to_julian_day(),
-			));
-		}
-		let format = format_description::parse("[year]-[month]-[day]T[hour]:[minute]:[second]").unwrap();
-		for event in events.events {
-			let dt = PrimitiveDateTime::parse(&event.start_time, &format)
-				.unwrap()
-				.assume_timezone(BERLIN)
-				.unwrap();
-			let julian_day = dt.to_julian_day();
-			if dt < time {
-				continue;
-			}
-			let duration = if let Some(end_time) = event.end_time.as_ref() {
-				let dt2 = PrimitiveDateTime::parse(end_time, &format)
-					.unwrap()
-					.assume_timezone(BERLIN)
-					.unwrap();
-				(dt2.sub(dt).as_seconds_f32() / 60.0) as i32
-			} else {
-				30
-			};
-			all_events.push((
-				dt.weekday().number_days_from_monday() as _,
-				dt.hour() as _,
-				dt.minute() as _,
-				duration,
-				event.name,
-				julian_day,
-			));
+		let upcoming = events.upcoming(time, UPCOMING_DAYS, tz);
+		for e in &upcoming.errors {
+			eprintln!("warning: skipping {}", e);
 		}
-		let today = time.date().to_julian_day();
-		let weekday = time.weekday().number_days_from_monday() as i32;
-		all_events.sort_by_key(|x| (x.5, ((x.0 + 7) - weekday) % 7, x.1, x.2));
-		//println!("{:?}", all_events);
-		let mut time_until_first = None;
+		let all_events = upcoming.occurrences;
+		let today = time.date();
+		let time_until_first = all_events
+			.iter()
+			.map(|x| x.start)
+			.find(|&x| x >= time)
+			.map(|x| (x - time).whole_seconds() as i32);
 		let colors = vec![
 			Rgb565::new(0xff >> 3, 0xff >> 2, 0x00 >> 3),
 			Rgb565::new(0xff >> 3, 0x00 >> 2, 0xff >> 3),
@@ -300,38 +268,17 @@ impl<D: DrawTarget<Color = Rgb565>> DrawWithContext<D> for Measurements {
 			Rgb565::new(0xff >> 3, 0xff >> 2, 0xff >> 3),
 		];
 		for i in 0..5 {
-			let day = (weekday + i) % 7;
+			let midnight = raspi_lib::local_time(today + time::Duration::days(i as i64), Time::MIDNIGHT, tz);
 			for hour in 0..24 {
-				for minute in 0..60 {
-					if minute % 6 != 0 {
-						continue;
-					}
-
+				for minute in (0..60).step_by(6) {
 					if i == 0 && hour == time.hour() as i32 && minute == (time.minute() as i32 / 6) * 6 {
 						bits.push((i, hour, minute / 6, Some(Rgb565::new(0xff, 0x00, 0xff))));
 					}
-
+					let slot = midnight + time::Duration::minutes((hour * 60 + minute) as i64);
 					for (event_idx, event) in all_events.iter().enumerate() {
-						if event.0 != day || event.5 < today || event.5 - today > 4 {
-							continue;
-						}
-						let event_start = event.1 * 60 + event.2;
-						let event_end = event_start + event.3;
-						let now = hour * 60 + minute;
-						let now2 = hour * 60 + minute + 6;
-						if now2 > event_start && now < event_end {
+						if event.overlaps(slot, slot + time::Duration::minutes(6)) {
 							bits.push((i, hour, minute / 6, colors.get(event_idx).copied()));
 						}
-						if time_until_first.is_none()
-							&& (i > 0
-								|| event.1 > time.hour() as i32
-								|| (event.1 == time.hour() as i32 && event.2 >= time.minute() as i32))
-						{
-							time_until_first = Some(
-								((i * 24 + event.1) * 60 + event.2) * 60
-									- (time.hour() as i32 * 60 + time.minute() as i32) * 60,
-							);
-						}
 					}
 				}
 			}
@@ -348,56 +295,60 @@ impl<D: DrawTarget<Color = Rgb565>> DrawWithContext<D> for Measurements {
 		}
 		if self.mode == MeasurementsMode::Events {
 			for (i, event) in all_events.iter().take(7).enumerate() {
-				let text = if event.4.len() > 19 {
-					&event.4[0..event.4.floor_char_boundary(19)]
+				let text = if event.name.len() > 19 {
+					&event.name[0..event.name.floor_char_boundary(19)]
 				} else {
-					&event.4
+					&event.name
 				};
-				let day = event.0 as usize;
+				let start = event.start.to_timezone(tz);
 				let y = y + 64 + 9 * i as i32 + 5;
-				if event.5 > today && event.5 - today > 7 {
-					let dt = Date::from_julian_day(event.5).unwrap();
-					Text::new(
-						&format!("{}.{}.", dt.day(), dt.month() as u8),
-						(0, y).into(),
-						text_style_4x6,
-					)
-					.draw(disp)?;
+				if (start.date() - today).whole_days() > 7 {
+					Text::new(&locale.date(start.date()), (0, y).into(), text_style_4x6).draw(disp)?;
 				} else {
+					let (first, second) = weekday(start.weekday());
 					text_style_6x9.set_text_color(Some(Rgb565::new(0xff, 0xff, 0xff)));
-					Text::new(days[day].0, (x, y).into(), text_style_6x9).draw(disp)?;
-					Text::new(days[day].1, (x + 6, y).into(), text_style4).draw(disp)?;
+					Text::new(first, (x, y).into(), text_style_6x9).draw(disp)?;
+					Text::new(second, (x + 6, y).into(), text_style4).draw(disp)?;
 				}
 				text_style_6x9.set_text_color(Some(colors[i]));
 				Text::new(text, (x + 14, y).into(), text_style_6x9).draw(disp)?;
 			}
 		} else if self.mode == MeasurementsMode::Temps {
-			let diff = global_max - global_min;
-			let x = 0;
-			let y = 64;
-			let scaley = 63;
-			let scalex = 2;
 			vals.reverse();
-			for (i, (a, b)) in vals.into_iter().enumerate() {
-				let x = x + i as i32 * scalex;
-				let y1 = y + (global_max - b) * scaley / diff;
-				let y2 = y + (global_max - a) * scaley / diff;
-				let height = y2 - y1 + 1;
-				let rect = Rectangle::new((x, y1).into(), (scalex as u32, height as u32).into());
-				disp.fill_solid(&rect, Rgb565::new(0xff, 0xff, 0xff))?;
+			draw_range_graph(disp, &vals, (global_min, global_max), 64, 63, text_style_6x9, |x| {
+				format!("{}", x as f32 / 10.0)
+			})?;
+		} else if self.mode == MeasurementsMode::AirQuality {
+			let readings = storage.latest(AIR_SENSOR, Quantity::Eco2).and_then(|eco2| {
+				let tvoc = storage.latest(AIR_SENSOR, Quantity::Tvoc)?;
+				Ok((eco2, tvoc, storage.recent(AIR_SENSOR, Quantity::Eco2, AIR_READINGS)?))
+			});
+			let (latest, recent) = match readings {
+				Ok((Some(eco2), Some(tvoc), recent)) => (Some((eco2, tvoc)), recent),
+				Ok((_, _, recent)) => (None, recent),
+				Err(e) => {
+					eprintln!("error: {}", e);
+					(None, vec![])
+				},
+			};
+			// one value per line, both do not fit the width of the display
+			let lines = match latest {
+				Some((eco2, tvoc)) => [format!("CO2 {:.0}ppm", eco2.value), format!("VOC {:.0}ppb", tvoc.value)],
+				None => ["no air quality data".to_owned(), String::new()],
+			};
+			for (idx, line) in lines.iter().enumerate() {
+				Text::new(line, (0, 64 + 8 + 9 * idx as i32).into(), text_style_6x9).draw(disp)?;
 			}
-			Text::new(
-				&format!("{}", global_max as f32 / 10.0),
-				(100, 64 + 10).into(),
-				text_style_6x9,
-			)
-			.draw(disp)?;
-			Text::new(
-				&format!("{}", global_min as f32 / 10.0),
-				(100, 64 + 50).into(),
-				text_style_6x9,
-			)
-			.draw(disp)?;
+			let eco2: Vec<i32> = recent.iter().map(|x| x.value as i32).collect();
+			// 30 minutes per bar
+			let mut vals: Vec<(i32, i32)> = eco2
+				.chunks(AIR_READINGS / 48)
+				.map(|x| (*x.iter().min().unwrap(), *x.iter().max().unwrap()))
+				.collect();
+			vals.reverse();
+			let min = vals.iter().map(|x| x.0).min().unwrap_or(0);
+			let max = vals.iter().map(|x| x.1).max().unwrap_or(0);
+			draw_range_graph(disp, &vals, (min, max), 64 + 20, 43, text_style_6x9, |x| x.to_string())?;
 		}
 		if let Some(secs) = time_until_first {
 			let days = secs / (24 * 60 * 60);
@@ -405,10 +356,8 @@ impl<D: DrawTarget<Color = Rgb565>> DrawWithContext<D> for Measurements {
 			let minutes = secs / 60 % 60;
 			let text = if days > 0 {
 				String::new()
-			} else if hours > 0 {
-				format!("{}h{}m", hours, minutes)
-			} else if minutes > 0 {
-				format!("{}m", minutes)
+			} else if hours > 0 || minutes > 0 {
+				locale.countdown(hours as i64, minutes as i64)
 			} else {
 				"?".into()
 			};
@@ -421,11 +370,44 @@ impl<D: DrawTarget<Color = Rgb565>> DrawWithContext<D> for Measurements {
 	}
 }
 
+/// Bar graph of (min, max) pairs, oldest first, two pixels per bar.
+/// The bounds are labelled on the right using `label`.
+fn draw_range_graph<D: DrawTarget<Color = Rgb565>>(
+	disp: &mut D,
+	vals: &[(i32, i32)],
+	(global_min, global_max): (i32, i32),
+	y: i32,
+	height: i32,
+	text_style: MonoTextStyle<Rgb565>,
+	label: impl Fn(i32) -> String,
+) -> Result<(), D::Error> {
+	let diff = (global_max - global_min).max(1);
+	let scalex = 2;
+	for (i, &(a, b)) in vals.iter().enumerate() {
+		let x = i as i32 * scalex;
+		let y1 = y + (global_max - b) * height / diff;
+		let y2 = y + (global_max - a) * height / diff;
+		let rect = Rectangle::new((x, y1).into(), (scalex as u32, (y2 - y1 + 1) as u32).into());
+		disp.fill_solid(&rect, Rgb565::new(0xff, 0xff, 0xff))?;
+	}
+	Text::new(&label(global_max), (100, y + 10).into(), text_style).draw(disp)?;
+	Text::new(&label(global_min), (100, y + height - 13).into(), text_style).draw(disp)?;
+	Ok(())
+}
+
 impl<D: DrawTarget<Color = Rgb565>> Draw<D> for Measurements {
 	fn draw(&self, _disp: &mut D, _rng: &mut Rng) -> Result<bool, <D as DrawTarget>::Error> {
 		panic!("draw without ctx");
 	}
 
+	fn redraw(&mut self) {
+		*self.drawn.get_mut() = false;
+	}
+
+	fn name(&self) -> &'static str {
+		<Self as Screensaver<D>>::id(self)
+	}
+
 	fn as_any(&self) -> &dyn Any {
 		self
 	}
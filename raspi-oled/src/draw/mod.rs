@@ -1,4 +1,8 @@
+mod agenda;
+pub use agenda::Agenda;
 mod measurements;
 pub use measurements::Measurements;
+mod month;
+pub use month::MonthView;
 mod totp;
 pub use totp::Totp;
@@ -0,0 +1,211 @@
+use std::{any::Any, cell::RefCell, convert::TryFrom};
+
+use embedded_graphics::{
+	mono_font::{ascii::FONT_6X9, iso_8859_1::FONT_10X20, MonoTextStyle},
+	pixelcolor::Rgb565,
+	prelude::*,
+	primitives::Rectangle,
+	text::{Baseline, Text},
+	Drawable,
+};
+use raspi_lib::{Draw, Screensaver};
+use time::{Date, Duration, Month, Time, Weekday};
+use time_tz::Tz;
+
+use crate::{
+	context::{Context, ContextDefault, DrawWithContext, Rng, BLACK},
+	Events,
+};
+
+/// Characters of the title that fit the display.
+const TITLE_WIDTH: usize = 12;
+
+/// Width and height of a day.
+const CELL: i32 = 16;
+/// Top of the first week.
+const GRID_Y: i32 = 30;
+
+const DIM: Rgb565 = Rgb565::new(0x0f, 0x1f, 0x0f);
+const TODAY: Rgb565 = Rgb565::new(0x1f, 0x00, 0x1f);
+const DOT: Rgb565 = Rgb565::YELLOW;
+
+/// Month grid with ISO week numbers, today highlighted and a dot below days with events.
+/// Up and down show the previous and next month, see [`menu::press`](crate::menu::press).
+#[derive(Debug, Default)]
+pub struct MonthView {
+	/// Months after the current one.
+	offset: i32,
+	/// Today and the offset of the last drawn frame.
+	drawn: RefCell<Option<(Date, i32)>>,
+}
+
+impl MonthView {
+	pub fn next_month(&mut self) {
+		self.offset += 1;
+	}
+
+	pub fn previous_month(&mut self) {
+		self.offset -= 1;
+	}
+}
+
+/// First day of the month `offset` months after the month of `today`.
+fn first_day(today: Date, offset: i32) -> Date {
+	let months = today.year() * 12 + today.month() as i32 - 1 + offset;
+	let month = Month::try_from((months.rem_euclid(12) + 1) as u8).unwrap();
+	Date::from_calendar_date(months.div_euclid(12), month, 1).unwrap()
+}
+
+/// Whether there are events on each day of the month starting at `first`.
+fn busy_days(events: &Events, first: Date, tz: &Tz) -> Vec<bool> {
+	let days = first.month().length(first.year());
+	let midnight = |day: u8| raspi_lib::local_time(first + Duration::days(day as i64), Time::MIDNIGHT, tz);
+	let occurrences = events.between(midnight(0), midnight(days), tz).occurrences;
+	(0..days)
+		.map(|day| occurrences.iter().any(|x| x.overlaps(midnight(day), midnight(day + 1))))
+		.collect()
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Screensaver<D> for MonthView {
+	fn id(&self) -> &'static str {
+		"month"
+	}
+
+	fn convert_draw(&self) -> Box<dyn Draw<D>> {
+		Box::new(MonthView::default())
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> DrawWithContext<D> for MonthView {
+	fn draw_with_ctx(&self, ctx: &ContextDefault<D>, disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
+		let tz = ctx.timezone();
+		let today = ctx.now().date();
+		if *self.drawn.borrow() == Some((today, self.offset)) {
+			return Ok(false);
+		}
+		*self.drawn.borrow_mut() = Some((today, self.offset));
+		let first = first_day(today, self.offset);
+		// recurring iCalendar events are expanded from the start of the month
+		let from = raspi_lib::local_time(first, Time::MIDNIGHT, tz);
+		let events = ctx.load_events(from);
+		let busy = busy_days(&events, first, tz);
+
+		disp.clear(BLACK)?;
+		let locale = ctx.locale();
+		let month = locale.month(first.month());
+		let mut title = format!("{} {}", month, first.year());
+		if title.chars().count() > TITLE_WIDTH {
+			let short: String = month.chars().take(3).collect();
+			title = format!("{} {}", short, first.year());
+		}
+		let x = (128 - 10 * title.chars().count() as i32) / 2;
+		let large = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+		Text::with_baseline(&title, Point::new(x, 0), large, Baseline::Top).draw(disp)?;
+		let dim = MonoTextStyle::new(&FONT_6X9, DIM);
+		let normal = MonoTextStyle::new(&FONT_6X9, Rgb565::WHITE);
+		let mut weekday = Weekday::Monday;
+		for idx in 0..7 {
+			let x = (idx + 1) * CELL + 5;
+			let style = if idx >= 5 { dim } else { normal };
+			let letter = &locale.weekday(weekday)[..1];
+			Text::with_baseline(letter, Point::new(x, 20), style, Baseline::Top).draw(disp)?;
+			weekday = weekday.next();
+		}
+		let mut monday = first - Duration::days(first.weekday().number_days_from_monday() as i64);
+		let mut y = GRID_Y;
+		while monday.month() == first.month() || monday < first {
+			let week = format!("{:2}", monday.iso_week());
+			Text::with_baseline(&week, Point::new(2, y + 3), dim, Baseline::Top).draw(disp)?;
+			for idx in 0..7 {
+				let date = monday + Duration::days(idx);
+				if date.month() != first.month() {
+					continue;
+				}
+				let x = (idx as i32 + 1) * CELL;
+				if date == today {
+					disp.fill_solid(
+						&Rectangle::new(Point::new(x, y), Size::new(CELL as u32, CELL as u32)),
+						TODAY,
+					)?;
+				}
+				let style = if idx >= 5 { dim } else { normal };
+				let text = format!("{:2}", date.day());
+				Text::with_baseline(&text, Point::new(x + 2, y + 3), style, Baseline::Top).draw(disp)?;
+				if busy[date.day() as usize - 1] {
+					disp.fill_solid(&Rectangle::new(Point::new(x + 7, y + 13), Size::new(2, 2)), DOT)?;
+				}
+			}
+			monday += Duration::weeks(1);
+			y += CELL;
+		}
+		Ok(true)
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Draw<D> for MonthView {
+	fn draw(&self, _disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
+		panic!("draw without ctx");
+	}
+
+	fn redraw(&mut self) {
+		*self.drawn.get_mut() = None;
+	}
+
+	fn name(&self) -> &'static str {
+		"month"
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+#[test]
+fn test_month_view() {
+	use crate::{Event, Weekly};
+
+	let tz = raspi_lib::DEFAULT_TIMEZONE;
+	let today = Date::from_calendar_date(2024, Month::May, 3).unwrap();
+	assert_eq!(
+		first_day(today, 0),
+		Date::from_calendar_date(2024, Month::May, 1).unwrap()
+	);
+	assert_eq!(
+		first_day(today, -5),
+		Date::from_calendar_date(2023, Month::December, 1).unwrap()
+	);
+	assert_eq!(
+		first_day(today, 8),
+		Date::from_calendar_date(2025, Month::January, 1).unwrap()
+	);
+
+	let events = Events {
+		events: vec![Event {
+			name: "Night shift".to_owned(),
+			start_time: "2024-05-30T22:00:00".to_owned(),
+			end_time: Some("2024-06-01T06:00:00".to_owned()),
+			alert_minutes: None,
+		}],
+		// Mondays
+		weekly: vec![Weekly {
+			name: "Sports".to_owned(),
+			day: 0,
+			hour: 19,
+			minute: 0,
+			duration: 60,
+			alert_minutes: None,
+			start_date: None,
+			end_date: None,
+			interval: 1,
+			skip: vec![],
+		}],
+		vacations: vec![],
+	};
+	let busy = busy_days(&events, first_day(today, 0), tz);
+	let days: Vec<_> = (1..=31).filter(|x| busy[*x as usize - 1]).collect();
+	assert_eq!(days, [6, 13, 20, 27, 30, 31]);
+}
@@ -105,6 +105,14 @@ impl<D: DrawTarget<Color = Rgb565>> Draw<D> for Totp {
 		Ok(true)
 	}
 
+	fn redraw(&mut self) {
+		self.codes.get_mut().clear();
+	}
+
+	fn name(&self) -> &'static str {
+		"totp"
+	}
+
 	fn as_any(&self) -> &dyn Any {
 		self
 	}
@@ -0,0 +1,493 @@
+//! Events shown by the calendar view of [`Measurements`](crate::draw::Measurements) and `display_all`.
+
+use std::{
+	convert::TryFrom,
+	fmt, fs, io,
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use time::{macros::format_description, Date, Duration, OffsetDateTime, PrimitiveDateTime, Time};
+use time_tz::{OffsetDateTimeExt, Tz};
+
+use crate::ical::{self, IcalError};
+
+/// Days after now for which recurring iCalendar events are expanded.
+pub const ICAL_DAYS: i64 = 31;
+
+/// Days after today covered by the event list of the calendar view.
+pub const UPCOMING_DAYS: i64 = 31;
+
+/// Length of events without an end time.
+const DEFAULT_DURATION: Duration = Duration::minutes(30);
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Events {
+	pub events: Vec<Event>,
+	pub weekly: Vec<Weekly>,
+	/// Weekly events do not take place during these, set from the configuration.
+	#[serde(skip)]
+	pub vacations: Vec<Vacation>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+	pub name: String,
+	/// Local time, formatted like `2024-05-03T18:30:00`.
+	pub start_time: String,
+	pub end_time: Option<String>,
+	/// Minutes before the start to show the countdown, see [`CountdownAlert`](crate::schedule::countdown::CountdownAlert).
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub alert_minutes: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Weekly {
+	pub name: String,
+	/// 0 is Monday.
+	pub day: i32,
+	pub hour: i32,
+	pub minute: i32,
+	/// Minutes.
+	pub duration: i32,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub alert_minutes: Option<u32>,
+	/// First day, formatted like `2024-04-15`. Required if `interval` is more than 1.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub start_date: Option<String>,
+	/// Last day, inclusive.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub end_date: Option<String>,
+	/// Weeks between two occurrences, counted from the week of `start_date`.
+	#[serde(default = "default_interval", skip_serializing_if = "is_default_interval")]
+	pub interval: u32,
+	/// Days without an occurrence, e.g. holidays.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub skip: Vec<String>,
+}
+
+fn default_interval() -> u32 {
+	1
+}
+
+fn is_default_interval(interval: &u32) -> bool {
+	*interval == 1
+}
+
+/// Days off, formatted like `2024-07-22`. Both days are included.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Vacation {
+	pub start: String,
+	pub end: String,
+}
+
+impl Vacation {
+	/// First and last day, `None` if one of them is invalid.
+	pub fn dates(&self) -> Option<(Date, Date)> {
+		Some((parse_date(&self.start)?, parse_date(&self.end)?))
+	}
+
+	fn contains(&self, date: Date) -> bool {
+		self.dates().is_some_and(|(start, end)| start <= date && date <= end)
+	}
+}
+
+/// A single occurrence of an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+	pub name: String,
+	pub start: OffsetDateTime,
+	pub end: OffsetDateTime,
+	/// Lead time of the countdown, if set for this event.
+	pub alert: Option<Duration>,
+}
+
+impl Occurrence {
+	/// Whether the event takes place at some time between `from` and `to`.
+	pub fn overlaps(&self, from: OffsetDateTime, to: OffsetDateTime) -> bool {
+		self.start < to && self.end > from
+	}
+}
+
+/// Result of [`Events::upcoming`].
+#[derive(Debug, Default)]
+pub struct Upcoming {
+	/// Sorted by start.
+	pub occurrences: Vec<Occurrence>,
+	/// Events that were skipped.
+	pub errors: Vec<EventError>,
+}
+
+/// Invalid entry of an events file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventError {
+	/// Start or end time not formatted like `2024-05-03T18:30:00`.
+	Time {
+		name: String,
+		value: String,
+	},
+	EndBeforeStart {
+		name: String,
+	},
+	/// Weekly event with a day, time, duration, date or interval out of range.
+	Weekly {
+		name: String,
+	},
+}
+
+impl fmt::Display for EventError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			EventError::Time { name, value } => write!(f, "event {name:?}: invalid time {value:?}"),
+			EventError::EndBeforeStart { name } => write!(f, "event {name:?}: ends before it starts"),
+			EventError::Weekly { name } => write!(
+				f,
+				"weekly event {name:?}: invalid day, time, duration, date or interval"
+			),
+		}
+	}
+}
+
+impl std::error::Error for EventError {}
+
+impl Event {
+	pub fn parse(&self, tz: &Tz) -> Result<Occurrence, EventError> {
+		let start = parse_local(&self.name, &self.start_time, tz)?;
+		let end = match &self.end_time {
+			Some(end) => parse_local(&self.name, end, tz)?,
+			None => start + DEFAULT_DURATION,
+		};
+		if end < start {
+			return Err(EventError::EndBeforeStart {
+				name: self.name.clone(),
+			});
+		}
+		Ok(Occurrence {
+			name: self.name.clone(),
+			start,
+			end,
+			alert: self.alert_minutes.map(|x| Duration::minutes(x as i64)),
+		})
+	}
+}
+
+fn parse_local(name: &str, value: &str, tz: &Tz) -> Result<OffsetDateTime, EventError> {
+	let format = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]");
+	let time = PrimitiveDateTime::parse(value, format).map_err(|_| EventError::Time {
+		name: name.to_owned(),
+		value: value.to_owned(),
+	})?;
+	Ok(raspi_lib::local_time(time.date(), time.time(), tz))
+}
+
+fn parse_date(value: &str) -> Option<Date> {
+	Date::parse(value, format_description!("[year]-[month]-[day]")).ok()
+}
+
+impl Weekly {
+	/// Occurrences starting on the `days` days from the date of `from`, except on skipped days,
+	/// including those that started earlier and end after `from`.
+	pub fn occurrences(&self, from: OffsetDateTime, days: i64, tz: &Tz) -> Result<Vec<Occurrence>, EventError> {
+		let invalid = || EventError::Weekly {
+			name: self.name.clone(),
+		};
+		if !(0..7).contains(&self.day) || self.duration < 0 {
+			return Err(invalid());
+		}
+		let time = Time::from_hms(
+			u8::try_from(self.hour).map_err(|_| invalid())?,
+			u8::try_from(self.minute).map_err(|_| invalid())?,
+			0,
+		)
+		.map_err(|_| invalid())?;
+		let date = |value: &String| parse_date(value).ok_or_else(invalid);
+		let start_date = self.start_date.as_ref().map(date).transpose()?;
+		let end_date = self.end_date.as_ref().map(date).transpose()?;
+		let skip = self.skip.iter().map(date).collect::<Result<Vec<_>, _>>()?;
+		if self.interval == 0 || (self.interval > 1 && start_date.is_none()) {
+			return Err(invalid());
+		}
+		// Monday of the first week
+		let first_week = start_date.map(|x| x - Duration::days(x.weekday().number_days_from_monday() as i64));
+		let today = from.to_timezone(tz).date();
+		// days before today an occurrence can last into it
+		let earlier = (self.duration as i64 + 24 * 60 - 1) / (24 * 60);
+		let occurrences = (-earlier..days)
+			.map(|x| today + Duration::days(x))
+			.filter(|x| x.weekday().number_days_from_monday() as i32 == self.day)
+			.filter(|x| start_date.is_none_or(|start| *x >= start) && end_date.is_none_or(|end| *x <= end))
+			.filter(|x| first_week.is_none_or(|week| (*x - week).whole_weeks() % self.interval as i64 == 0))
+			.filter(|x| !skip.contains(x))
+			.map(|date| {
+				let start = raspi_lib::local_time(date, time, tz);
+				Occurrence {
+					name: self.name.clone(),
+					start,
+					end: start + Duration::minutes(self.duration as i64),
+					alert: self.alert_minutes.map(|x| Duration::minutes(x as i64)),
+				}
+			})
+			.filter(|x| x.end > from)
+			.collect();
+		Ok(occurrences)
+	}
+}
+
+#[derive(Debug)]
+pub enum EventsError {
+	Io(PathBuf, io::Error),
+	Json(PathBuf, serde_json::Error),
+	Ical(PathBuf, IcalError),
+}
+
+impl fmt::Display for EventsError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			EventsError::Io(path, e) => write!(f, "failed to read {}: {e}", path.display()),
+			EventsError::Json(path, e) => write!(f, "failed to parse {}: {e}", path.display()),
+			EventsError::Ical(path, e) => write!(f, "failed to parse {}: {e}", path.display()),
+		}
+	}
+}
+
+impl std::error::Error for EventsError {}
+
+impl Events {
+	/// Events taking place between `now` and the end of the `days`th day after today,
+	/// including those that already started.
+	pub fn upcoming(&self, now: OffsetDateTime, days: i64, tz: &Tz) -> Upcoming {
+		let today = now.to_timezone(tz).date();
+		let end = raspi_lib::local_time(today + Duration::days(days + 1), Time::MIDNIGHT, tz);
+		self.between(now, end, tz)
+	}
+
+	/// Events taking place at some time between `from` and `to`.
+	pub fn between(&self, from: OffsetDateTime, to: OffsetDateTime, tz: &Tz) -> Upcoming {
+		let days = (to.to_timezone(tz).date() - from.to_timezone(tz).date()).whole_days() + 1;
+		let mut upcoming = Upcoming::default();
+		let weekly = self.weekly.iter().map(|x| {
+			let occurrences = x.occurrences(from, days, tz)?;
+			Ok(occurrences
+				.into_iter()
+				.filter(|x| {
+					let date = x.start.to_timezone(tz).date();
+					!self.vacations.iter().any(|vacation| vacation.contains(date))
+				})
+				.collect())
+		});
+		let once = self.events.iter().map(|x| x.parse(tz).map(|x| vec![x]));
+		for result in weekly.chain(once) {
+			match result {
+				Ok(occurrences) => upcoming
+					.occurrences
+					.extend(occurrences.into_iter().filter(|x| x.overlaps(from, to))),
+				Err(e) => upcoming.errors.push(e),
+			}
+		}
+		upcoming
+			.occurrences
+			.sort_by(|a, b| (a.start, &a.name).cmp(&(b.start, &b.name)));
+		upcoming
+	}
+
+	/// Read the events JSON written by `refresh_json`, an iCalendar (`.ics`) file
+	/// or a directory, in which case all iCalendar files in it are merged.
+	///
+	/// iCalendar events are converted to local times in `tz`, recurring events are expanded
+	/// for the [`ICAL_DAYS`] days after `now`.
+	pub fn load(path: &Path, tz: &'static Tz, now: OffsetDateTime) -> Result<Events, EventsError> {
+		let io_error = |e| EventsError::Io(path.to_owned(), e);
+		if path.is_dir() {
+			let mut files = vec![];
+			for entry in fs::read_dir(path).map_err(io_error)? {
+				let file = entry.map_err(io_error)?.path();
+				if is_ical(&file) {
+					files.push(file);
+				}
+			}
+			files.sort();
+			let mut events = Events::default();
+			for file in files {
+				events.events.extend(load_ical(&file, tz, now)?);
+			}
+			return Ok(events);
+		}
+		if is_ical(path) {
+			return Ok(Events {
+				events: load_ical(path, tz, now)?,
+				..Events::default()
+			});
+		}
+		let json = fs::read_to_string(path).map_err(io_error)?;
+		serde_json::from_str(&json).map_err(|e| EventsError::Json(path.to_owned(), e))
+	}
+}
+
+fn is_ical(path: &Path) -> bool {
+	path.extension().is_some_and(|x| x.eq_ignore_ascii_case("ics"))
+}
+
+fn load_ical(path: &Path, tz: &'static Tz, now: OffsetDateTime) -> Result<Vec<Event>, EventsError> {
+	let text = fs::read_to_string(path).map_err(|e| EventsError::Io(path.to_owned(), e))?;
+	ical::parse(&text, tz, now, now + Duration::days(ICAL_DAYS)).map_err(|e| EventsError::Ical(path.to_owned(), e))
+}
+
+#[test]
+fn test_parse_events() {
+	let tz = raspi_lib::DEFAULT_TIMEZONE;
+	let event = |start: &str, end: Option<&str>| Event {
+		name: "x".to_owned(),
+		start_time: start.to_owned(),
+		end_time: end.map(str::to_owned),
+		alert_minutes: None,
+	};
+	let occurrence = event("2024-05-03T18:30:00", None).parse(tz).unwrap();
+	assert_eq!(occurrence.end - occurrence.start, DEFAULT_DURATION);
+	assert_eq!(occurrence.start.to_timezone(tz).hour(), 18);
+	assert_eq!(
+		event("2024-05-03 18:30", None).parse(tz),
+		Err(EventError::Time {
+			name: "x".to_owned(),
+			value: "2024-05-03 18:30".to_owned()
+		})
+	);
+	assert!(matches!(
+		event("2024-05-03T18:30:00", Some("2024-05-03T18:00:00")).parse(tz),
+		Err(EventError::EndBeforeStart { .. })
+	));
+}
+
+#[test]
+fn test_upcoming() {
+	let tz = raspi_lib::DEFAULT_TIMEZONE;
+	let event = |name: &str, start: &str, end: &str| Event {
+		name: name.to_owned(),
+		start_time: start.to_owned(),
+		end_time: Some(end.to_owned()),
+		alert_minutes: None,
+	};
+	let weekly = |name: &str, day, hour| Weekly {
+		name: name.to_owned(),
+		day,
+		hour,
+		minute: 0,
+		duration: 60,
+		alert_minutes: None,
+		start_date: None,
+		end_date: None,
+		interval: 1,
+		skip: vec![],
+	};
+	let events = Events {
+		events: vec![
+			event("over", "2024-05-01T08:00:00", "2024-05-01T09:00:00"),
+			event("night shift", "2024-05-02T22:00:00", "2024-05-03T06:00:00"),
+			event("vacation", "2024-04-29T00:00:00", "2024-05-06T00:00:00"),
+			event("later", "2024-05-20T10:00:00", "2024-05-20T11:00:00"),
+			event("broken", "tomorrow", "2024-05-03T06:00:00"),
+		],
+		// Friday, the 3rd and the 10th
+		weekly: vec![weekly("sports", 4, 19), weekly("invalid", 7, 0)],
+		vacations: vec![],
+	};
+	let now = raspi_lib::local_time(
+		time::Date::from_calendar_date(2024, time::Month::May, 3).unwrap(),
+		Time::from_hms(5, 0, 0).unwrap(),
+		tz,
+	);
+	let upcoming = events.upcoming(now, 7, tz);
+	let names: Vec<_> = upcoming
+		.occurrences
+		.iter()
+		.map(|x| (x.name.as_str(), x.start.to_timezone(tz).day()))
+		.collect();
+	assert_eq!(
+		names,
+		[("vacation", 29), ("night shift", 2), ("sports", 3), ("sports", 10)]
+	);
+	assert_eq!(upcoming.errors.len(), 2);
+}
+
+#[test]
+fn test_weekly_rules() {
+	let tz = raspi_lib::DEFAULT_TIMEZONE;
+	// every other Tuesday from April 16th to June 25th, not on May 14th
+	let weekly: Weekly = serde_json::from_str(
+		r#"{
+			"name": "class",
+			"day": 1,
+			"hour": 10,
+			"minute": 0,
+			"duration": 90,
+			"startDate": "2024-04-15",
+			"endDate": "2024-06-25",
+			"interval": 2,
+			"skip": ["2024-05-14"]
+		}"#,
+	)
+	.unwrap();
+	let mut events = Events {
+		weekly: vec![weekly],
+		..Events::default()
+	};
+	let from = raspi_lib::local_time(
+		time::Date::from_calendar_date(2024, time::Month::April, 1).unwrap(),
+		Time::MIDNIGHT,
+		tz,
+	);
+	let days = |events: &Events| -> Vec<_> {
+		let upcoming = events.between(from, from + Duration::days(120), tz);
+		assert!(upcoming.errors.is_empty());
+		upcoming
+			.occurrences
+			.iter()
+			.map(|x| {
+				let date = x.start.to_timezone(tz).date();
+				(date.month() as u8, date.day())
+			})
+			.collect()
+	};
+	assert_eq!(days(&events), [(4, 16), (4, 30), (5, 28), (6, 11), (6, 25)]);
+	events.vacations.push(Vacation {
+		start: "2024-05-25".to_owned(),
+		end: "2024-06-15".to_owned(),
+	});
+	assert_eq!(days(&events), [(4, 16), (4, 30), (6, 25)]);
+
+	// still running after midnight
+	let late = Weekly {
+		name: "late".to_owned(),
+		day: 0,
+		hour: 23,
+		minute: 0,
+		duration: 120,
+		alert_minutes: None,
+		start_date: None,
+		end_date: None,
+		interval: 1,
+		skip: vec![],
+	};
+	let tuesday = raspi_lib::local_time(
+		time::Date::from_calendar_date(2024, time::Month::April, 2).unwrap(),
+		Time::from_hms(0, 30, 0).unwrap(),
+		tz,
+	);
+	let occurrences = late.occurrences(tuesday, 1, tz).unwrap();
+	assert_eq!(occurrences.len(), 1);
+	assert_eq!(occurrences[0].end, tuesday + Duration::minutes(30));
+	assert!(late
+		.occurrences(tuesday + Duration::hours(1), 1, tz)
+		.unwrap()
+		.is_empty());
+
+	events.weekly[0].start_date = None;
+	assert_eq!(
+		events.between(from, from + Duration::days(7), tz).errors,
+		[EventError::Weekly {
+			name: "class".to_owned()
+		}]
+	);
+}
@@ -0,0 +1,141 @@
+//! Rendering without a display or window system.
+//!
+//! [`Headless`] drives [`ContextDefault::loop_iter`] against an in-memory [`FrameOutput`],
+//! using a [`FakeClock`] and a seeded [`Rng`] so the produced frames are reproducible.
+//! The [`NightMode`] dims the frames by scaling their colors, [`BurnIn`] protection is applied as on the device.
+
+use std::rc::Rc;
+
+use frame_output::FrameOutput;
+use rand_xoshiro::rand_core::SeedableRng;
+use raspi_lib::{Display, FakeClock};
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+	action::Action,
+	burn_in::BurnIn,
+	config::{Config, ConfigError},
+	context::{Context, ContextDefault, Rng},
+	menu::{self, Button},
+	night_mode::NightMode,
+};
+
+/// Delay after drawing a frame in milliseconds.
+pub const FRAME_INTERVAL: u64 = 66;
+
+pub struct Headless {
+	pub ctx: ContextDefault<Display<FrameOutput>>,
+	pub disp: Display<FrameOutput>,
+	pub clock: Rc<FakeClock>,
+	night: NightMode,
+	burn_in: BurnIn,
+	rng: Rng,
+	frame: usize,
+}
+
+impl Headless {
+	pub fn new(config: &Config, start: OffsetDateTime, seed: u64) -> Result<Self, ConfigError> {
+		let clock = Rc::new(FakeClock::new(start));
+		let ctx = ContextDefault::with_clock(config, clock.clone())?;
+		Ok(Headless {
+			ctx,
+			disp: Display::new(FrameOutput::new(128, 128)),
+			clock,
+			night: config.night_mode()?,
+			burn_in: config.burn_in(),
+			rng: Rng::seed_from_u64(seed),
+			frame: 0,
+		})
+	}
+
+	pub fn do_action(&self, action: Action) {
+		self.ctx.do_action(action);
+	}
+
+	/// Render the next frame, then advance the clock by [`FRAME_INTERVAL`].
+	/// Returns whether the frame changed.
+	pub fn step(&mut self) -> bool {
+		let now = self.ctx.now();
+		let mut changed = self.night.apply(&mut self.ctx, &mut self.disp, now).unwrap_or(false);
+		changed |= self.burn_in.apply(&mut self.ctx, &mut self.disp, now).unwrap_or(false);
+		let dirty = self.ctx.loop_iter(&mut self.disp, &mut self.rng) || changed;
+		self.clock.advance(Duration::milliseconds(FRAME_INTERVAL as i64));
+		self.frame += 1;
+		dirty
+	}
+
+	/// Press a button as on the device.
+	/// Returns whether the menu received it, which it does not if the press only woke the display.
+	pub fn press(&mut self, button: Button) -> bool {
+		let now = self.ctx.now();
+		let woke = self.night.wake(now, self.ctx.timezone()) | self.burn_in.input(now);
+		if !woke {
+			menu::press(&mut self.ctx, &mut self.disp, button);
+		}
+		let _ = self.night.apply(&mut self.ctx, &mut self.disp, now);
+		let _ = self.burn_in.apply(&mut self.ctx, &mut self.disp, now);
+		!woke
+	}
+
+	/// Render `frames` frames. `on_frame` receives the frame index, the display and the dirty flag.
+	pub fn run(&mut self, frames: usize, mut on_frame: impl FnMut(usize, &FrameOutput, bool)) {
+		for _ in 0..frames {
+			let idx = self.frame;
+			let dirty = self.step();
+			on_frame(idx, self.disp.panel(), dirty);
+		}
+	}
+}
+
+#[test]
+fn test_headless_deterministic() {
+	let config: Config = toml::from_str(
+		r#"
+		database = ":memory:"
+		screensavers = ["star"]
+
+		[[schedule]]
+		type = "reminder"
+		time = "23:40"
+		action = "star"
+		"#,
+	)
+	.unwrap();
+	let tz = config.timezone().unwrap();
+	let start = raspi_lib::local_time(
+		time::Date::from_calendar_date(2024, time::Month::May, 3).unwrap(),
+		time::Time::from_hms(23, 39, 58).unwrap(),
+		tz,
+	);
+	let render = || {
+		let mut headless = Headless::new(&config, start, 17381).unwrap();
+		let mut frames = vec![];
+		headless.run(60, |idx, disp, dirty| {
+			if dirty {
+				frames.push((idx, disp.buffer.clone()));
+			}
+		});
+		frames
+	};
+	let first = render();
+	// the clock, then the reminder kicks in after two seconds
+	assert_eq!(first[0].0, 0);
+	assert!(first.len() > 20, "{}", first.len());
+	assert!(first == render());
+}
+
+#[test]
+fn test_clock_golden() {
+	let config: Config = toml::from_str("database = \":memory:\"\nscreensavers = []\nschedule = []").unwrap();
+	let start = raspi_lib::local_time(
+		time::Date::from_calendar_date(2024, time::Month::May, 3).unwrap(),
+		time::Time::from_hms(23, 40, 0).unwrap(),
+		config.timezone().unwrap(),
+	);
+	let mut headless = Headless::new(&config, start, 17381).unwrap();
+	assert!(headless.step());
+	let golden = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("golden/clock_2340.png");
+	if let Err(e) = headless.disp.panel().compare_golden(&golden) {
+		panic!("{}", e);
+	}
+}
@@ -0,0 +1,645 @@
+//! Reader for iCalendar (RFC 5545) files.
+//!
+//! Only `VEVENT`s are read: `SUMMARY`, `DTSTART`, `DTEND` or `DURATION`, `RRULE` with a daily, weekly,
+//! monthly or yearly frequency (`INTERVAL`, `COUNT`, `UNTIL` and for weekly rules `BYDAY`),
+//! `EXDATE`, `RECURRENCE-ID` and `STATUS:CANCELLED`.
+//! Events with other recurrence rules only show up at their start.
+//! Time zones are looked up by their IANA name, `VTIMEZONE` definitions are ignored.
+//! Times without a (known) time zone are taken to be local.
+
+use std::{collections::HashMap, convert::TryFrom, fmt};
+
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, Weekday};
+use time_tz::{OffsetDateTimeExt, Tz};
+
+use crate::Event;
+
+/// Upper bound for the number of periods (days, weeks, ...) a rule is followed.
+const MAX_PERIODS: u32 = 100_000;
+
+#[derive(Debug)]
+pub enum IcalError {
+	/// Content line without a value.
+	Syntax { line: usize },
+	/// Property value that could not be parsed.
+	Invalid {
+		line: usize,
+		property: String,
+		value: String,
+	},
+	/// `VEVENT` starting at `line` without `DTSTART`.
+	MissingStart { line: usize },
+}
+
+impl fmt::Display for IcalError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			IcalError::Syntax { line } => write!(f, "line {line}: expected `NAME:value`"),
+			IcalError::Invalid { line, property, value } => write!(f, "line {line}: invalid {property} {value:?}"),
+			IcalError::MissingStart { line } => write!(f, "line {line}: event without DTSTART"),
+		}
+	}
+}
+
+impl std::error::Error for IcalError {}
+
+/// Occurrences of the events in `text` overlapping `from` to `to`, converted to local times in `tz`.
+pub fn parse(text: &str, tz: &'static Tz, from: OffsetDateTime, to: OffsetDateTime) -> Result<Vec<Event>, IcalError> {
+	let events = read_events(text, tz)?;
+	// occurrences moved or cancelled by a separate VEVENT
+	let mut overrides: HashMap<&str, Vec<OffsetDateTime>> = HashMap::new();
+	for event in &events {
+		if let (Some(uid), Some(id)) = (&event.uid, event.recurrence_id) {
+			overrides.entry(uid).or_default().push(id.resolve());
+		}
+	}
+	let mut occurrences = vec![];
+	for event in &events {
+		if event.cancelled {
+			continue;
+		}
+		let moved = match (&event.uid, event.recurrence_id) {
+			(Some(uid), None) => overrides.get(uid.as_str()).map_or(&[][..], |x| &x[..]),
+			_ => &[],
+		};
+		event.expand(from, to, moved, |start, end| {
+			occurrences.push(Event {
+				name: event.summary.clone(),
+				start_time: format_local(start, tz),
+				end_time: end.map(|x| format_local(x, tz)),
+				alert_minutes: event.alert.map(|x| x.whole_minutes() as u32),
+			});
+		});
+	}
+	Ok(occurrences)
+}
+
+fn format_local(time: OffsetDateTime, tz: &Tz) -> String {
+	let x = time.to_timezone(tz);
+	format!(
+		"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+		x.year(),
+		x.month() as u8,
+		x.day(),
+		x.hour(),
+		x.minute(),
+		x.second()
+	)
+}
+
+/// Content line, unfolded.
+struct Property {
+	line: usize,
+	/// Upper case.
+	name: String,
+	/// Upper case names, values without quotes.
+	params: Vec<(String, String)>,
+	value: String,
+}
+
+impl Property {
+	fn param(&self, name: &str) -> Option<&str> {
+		self.params.iter().find(|x| x.0 == name).map(|x| x.1.as_str())
+	}
+
+	fn invalid(&self) -> IcalError {
+		IcalError::Invalid {
+			line: self.line,
+			property: self.name.clone(),
+			value: self.value.clone(),
+		}
+	}
+}
+
+/// Split `text` into content lines, joining folded lines.
+fn properties(text: &str) -> Result<Vec<Property>, IcalError> {
+	let mut lines: Vec<(usize, String)> = vec![];
+	for (idx, line) in text.lines().enumerate() {
+		match (line.strip_prefix(|c| c == ' ' || c == '\t'), lines.last_mut()) {
+			(Some(rest), Some(last)) => last.1 += rest,
+			_ if line.is_empty() => {},
+			_ => lines.push((idx + 1, line.to_owned())),
+		}
+	}
+	lines.into_iter().map(|(line, text)| property(line, &text)).collect()
+}
+
+fn property(line: usize, text: &str) -> Result<Property, IcalError> {
+	// split at `;` and the first `:` outside of quoted parameter values
+	let mut parts = vec![];
+	let mut quoted = false;
+	let mut part_start = 0;
+	let mut value = None;
+	for (idx, c) in text.char_indices() {
+		match c {
+			'"' => quoted = !quoted,
+			';' if !quoted => {
+				parts.push(&text[part_start..idx]);
+				part_start = idx + 1;
+			},
+			':' if !quoted => {
+				parts.push(&text[part_start..idx]);
+				value = Some(&text[idx + 1..]);
+				break;
+			},
+			_ => {},
+		}
+	}
+	let value = value.ok_or(IcalError::Syntax { line })?;
+	let mut params = vec![];
+	for param in &parts[1..] {
+		let (name, value) = param.split_once('=').ok_or(IcalError::Syntax { line })?;
+		params.push((name.to_ascii_uppercase(), value.trim_matches('"').to_owned()));
+	}
+	Ok(Property {
+		line,
+		name: parts[0].to_ascii_uppercase(),
+		params,
+		value: value.to_owned(),
+	})
+}
+
+/// Text value with escapes resolved.
+fn unescape(value: &str) -> String {
+	let mut text = String::with_capacity(value.len());
+	let mut chars = value.chars();
+	while let Some(c) = chars.next() {
+		if c != '\\' {
+			text.push(c);
+			continue;
+		}
+		match chars.next() {
+			Some('n') | Some('N') => text.push(' '),
+			Some(c) => text.push(c),
+			None => {},
+		}
+	}
+	text
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Zone {
+	Utc,
+	Local(&'static Tz),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DateTime {
+	value: PrimitiveDateTime,
+	zone: Zone,
+	/// Date without a time, i.e. an all-day event.
+	all_day: bool,
+}
+
+impl DateTime {
+	fn resolve(&self) -> OffsetDateTime {
+		match self.zone {
+			Zone::Utc => self.value.assume_utc(),
+			Zone::Local(tz) => raspi_lib::local_time(self.value.date(), self.value.time(), tz),
+		}
+	}
+
+	/// Same wall clock time on another day.
+	fn on(&self, date: Date) -> DateTime {
+		DateTime {
+			value: PrimitiveDateTime::new(date, self.value.time()),
+			..*self
+		}
+	}
+}
+
+fn parse_date(text: &str) -> Option<Date> {
+	if text.len() != 8 || !text.bytes().all(|x| x.is_ascii_digit()) {
+		return None;
+	}
+	let month = Month::try_from(text[4..6].parse::<u8>().ok()?).ok()?;
+	Date::from_calendar_date(text[..4].parse().ok()?, month, text[6..].parse().ok()?).ok()
+}
+
+fn parse_time(text: &str) -> Option<Time> {
+	if text.len() != 6 || !text.bytes().all(|x| x.is_ascii_digit()) {
+		return None;
+	}
+	let second = text[4..].parse::<u8>().ok()?.min(59); // leap seconds
+	Time::from_hms(text[..2].parse().ok()?, text[2..4].parse().ok()?, second).ok()
+}
+
+/// Parse a date or date-time value, using the `TZID` of `prop` or else `tz`.
+fn parse_date_time(prop: &Property, text: &str, tz: &'static Tz) -> Result<DateTime, IcalError> {
+	if let Some(date) = parse_date(text) {
+		return Ok(DateTime {
+			value: date.midnight(),
+			zone: Zone::Local(tz),
+			all_day: true,
+		});
+	}
+	let (text, utc) = match text.strip_suffix('Z') {
+		Some(text) => (text, true),
+		None => (text, false),
+	};
+	let (date, time) = text.split_once('T').ok_or_else(|| prop.invalid())?;
+	let value = PrimitiveDateTime::new(
+		parse_date(date).ok_or_else(|| prop.invalid())?,
+		parse_time(time).ok_or_else(|| prop.invalid())?,
+	);
+	let zone = if utc {
+		Zone::Utc
+	} else {
+		// some clients prefix the name with a slash
+		let tz = prop
+			.param("TZID")
+			.and_then(|x| raspi_lib::timezone(x.trim_start_matches('/')))
+			.unwrap_or(tz);
+		Zone::Local(tz)
+	};
+	Ok(DateTime {
+		value,
+		zone,
+		all_day: false,
+	})
+}
+
+/// Parse a duration like `PT1H30M` or `P1D`.
+fn parse_duration(text: &str) -> Option<Duration> {
+	let text = text.strip_prefix('+').unwrap_or(text).strip_prefix('P')?;
+	let mut total = Duration::ZERO;
+	let mut number = None;
+	let mut in_time = false;
+	for c in text.chars() {
+		if let Some(digit) = c.to_digit(10) {
+			number = Some(number.unwrap_or(0i64) * 10 + digit as i64);
+			continue;
+		}
+		if c == 'T' {
+			in_time = true;
+			continue;
+		}
+		let n = number.take()?;
+		total += match (c, in_time) {
+			('W', false) => Duration::weeks(n),
+			('D', false) => Duration::days(n),
+			('H', true) => Duration::hours(n),
+			('M', true) => Duration::minutes(n),
+			('S', true) => Duration::seconds(n),
+			_ => return None,
+		};
+	}
+	number.is_none().then_some(total)
+}
+
+fn parse_weekday(text: &str) -> Option<Weekday> {
+	Some(match text {
+		"MO" => Weekday::Monday,
+		"TU" => Weekday::Tuesday,
+		"WE" => Weekday::Wednesday,
+		"TH" => Weekday::Thursday,
+		"FR" => Weekday::Friday,
+		"SA" => Weekday::Saturday,
+		"SU" => Weekday::Sunday,
+		_ => return None,
+	})
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+	Daily,
+	Weekly,
+	Monthly,
+	Yearly,
+}
+
+#[derive(Debug)]
+struct Rule {
+	frequency: Frequency,
+	interval: u32,
+	count: Option<u32>,
+	until: Option<DateTime>,
+	/// Days of weekly rules, sorted from Monday.
+	by_day: Vec<Weekday>,
+}
+
+/// `None` if the rule uses unsupported parts.
+fn parse_rule(prop: &Property, tz: &'static Tz) -> Result<Option<Rule>, IcalError> {
+	let mut rule = Rule {
+		frequency: Frequency::Daily,
+		interval: 1,
+		count: None,
+		until: None,
+		by_day: vec![],
+	};
+	let mut frequency = None;
+	for part in prop.value.split(';') {
+		let (key, value) = part.split_once('=').ok_or_else(|| prop.invalid())?;
+		match key.to_ascii_uppercase().as_str() {
+			"FREQ" => {
+				frequency = Some(match value.to_ascii_uppercase().as_str() {
+					"DAILY" => Frequency::Daily,
+					"WEEKLY" => Frequency::Weekly,
+					"MONTHLY" => Frequency::Monthly,
+					"YEARLY" => Frequency::Yearly,
+					_ => return Ok(None),
+				})
+			},
+			"INTERVAL" => rule.interval = value.parse().ok().filter(|&x| x > 0).ok_or_else(|| prop.invalid())?,
+			"COUNT" => rule.count = Some(value.parse().map_err(|_| prop.invalid())?),
+			"UNTIL" => rule.until = Some(parse_date_time(prop, value, tz)?),
+			"BYDAY" => {
+				// days with an ordinal like `2TU` are not supported
+				for day in value.split(',') {
+					match parse_weekday(&day.to_ascii_uppercase()) {
+						Some(day) => rule.by_day.push(day),
+						None => return Ok(None),
+					}
+				}
+			},
+			// the week always starts on Monday
+			"WKST" => {},
+			_ => return Ok(None),
+		}
+	}
+	rule.frequency = frequency.ok_or_else(|| prop.invalid())?;
+	if !rule.by_day.is_empty() && rule.frequency != Frequency::Weekly {
+		return Ok(None);
+	}
+	rule.by_day.sort_by_key(|x| x.number_days_from_monday());
+	rule.by_day.dedup();
+	Ok(Some(rule))
+}
+
+impl Rule {
+	/// Dates of the `period`th period after `start`, in order.
+	/// `None` if the period lies beyond the range of [`Date`].
+	fn dates(&self, start: Date, period: u32) -> Option<Vec<Date>> {
+		let n = period.checked_mul(self.interval)? as i64;
+		let year = |offset: i64| {
+			let year = i32::try_from(start.year() as i64 + offset).ok()?;
+			(year <= Date::MAX.year()).then_some(year)
+		};
+		Some(match self.frequency {
+			Frequency::Daily => vec![start.checked_add(Duration::days(n))?],
+			Frequency::Weekly => {
+				let monday = start - Duration::days(start.weekday().number_days_from_monday() as i64);
+				let week = monday.checked_add(Duration::weeks(n))?;
+				if self.by_day.is_empty() {
+					return Some(vec![start.checked_add(Duration::weeks(n))?]);
+				}
+				self.by_day
+					.iter()
+					.filter_map(|x| week.checked_add(Duration::days(x.number_days_from_monday() as i64)))
+					.filter(|&x| x >= start)
+					.collect()
+			},
+			Frequency::Monthly => {
+				let months = start.month() as i64 - 1 + n;
+				let month = Month::try_from(months.rem_euclid(12) as u8 + 1).unwrap();
+				// months without that day are skipped
+				Date::from_calendar_date(year(months.div_euclid(12))?, month, start.day())
+					.into_iter()
+					.collect()
+			},
+			Frequency::Yearly => Date::from_calendar_date(year(n)?, start.month(), start.day())
+				.into_iter()
+				.collect(),
+		})
+	}
+}
+
+struct VEvent {
+	uid: Option<String>,
+	summary: String,
+	start: DateTime,
+	end: Option<DateTime>,
+	duration: Option<Duration>,
+	rule: Option<Rule>,
+	exdates: Vec<OffsetDateTime>,
+	recurrence_id: Option<DateTime>,
+	cancelled: bool,
+	/// Longest lead time of the alarms relative to the start.
+	alert: Option<Duration>,
+}
+
+fn read_events(text: &str, tz: &'static Tz) -> Result<Vec<VEvent>, IcalError> {
+	let mut events = vec![];
+	// line of BEGIN:VEVENT and the properties read so far
+	let mut current: Option<(usize, Vec<Property>)> = None;
+	// components nested in the event, e.g. VALARM
+	let mut nested = 0;
+	for prop in properties(text)? {
+		let component = prop.value.to_ascii_uppercase();
+		match (prop.name.as_str(), &mut current) {
+			("BEGIN", None) if component == "VEVENT" => current = Some((prop.line, vec![])),
+			("BEGIN", Some(_)) => nested += 1,
+			("END", Some(_)) if nested > 0 => nested -= 1,
+			("END", Some(_)) if component == "VEVENT" => {
+				let (line, props) = current.take().unwrap();
+				events.push(event(line, props, tz)?);
+			},
+			(_, Some((_, props))) if nested == 0 => props.push(prop),
+			// alarm times, the only properties of nested components that are used
+			(_, Some((_, props))) if prop.name == "TRIGGER" => props.push(prop),
+			_ => {},
+		}
+	}
+	Ok(events)
+}
+
+fn event(line: usize, props: Vec<Property>, tz: &'static Tz) -> Result<VEvent, IcalError> {
+	let mut uid = None;
+	let mut summary = String::new();
+	let mut start = None;
+	let mut end = None;
+	let mut duration = None;
+	let mut rule = None;
+	let mut exdates = vec![];
+	let mut recurrence_id = None;
+	let mut cancelled = false;
+	let mut alert = None;
+	for prop in &props {
+		match prop.name.as_str() {
+			"UID" => uid = Some(prop.value.clone()),
+			"SUMMARY" => summary = unescape(&prop.value),
+			"DTSTART" => start = Some(parse_date_time(prop, &prop.value, tz)?),
+			"DTEND" => end = Some(parse_date_time(prop, &prop.value, tz)?),
+			"DURATION" => duration = Some(parse_duration(&prop.value).ok_or_else(|| prop.invalid())?),
+			"RRULE" => rule = parse_rule(prop, tz)?,
+			"EXDATE" => {
+				for value in prop.value.split(',') {
+					exdates.push(parse_date_time(prop, value, tz)?.resolve());
+				}
+			},
+			"RECURRENCE-ID" => recurrence_id = Some(parse_date_time(prop, &prop.value, tz)?),
+			"STATUS" => cancelled = prop.value.eq_ignore_ascii_case("CANCELLED"),
+			// absolute alarm times and those relative to the end are ignored
+			"TRIGGER" if prop.param("VALUE").is_none() && prop.param("RELATED") != Some("END") => {
+				let lead = prop.value.strip_prefix('-').and_then(parse_duration);
+				alert = alert.max(lead.filter(|x| *x > Duration::ZERO));
+			},
+			_ => {},
+		}
+	}
+	Ok(VEvent {
+		uid,
+		summary,
+		start: start.ok_or(IcalError::MissingStart { line })?,
+		end,
+		duration,
+		rule,
+		exdates,
+		recurrence_id,
+		cancelled,
+		alert,
+	})
+}
+
+impl VEvent {
+	/// Start and end of the occurrence starting at `start`, `None` if the event has no length.
+	fn occurrence(&self, start: DateTime) -> (OffsetDateTime, Option<OffsetDateTime>) {
+		let begin = start.resolve();
+		if self.start.all_day {
+			// whole days, independent of DST changes
+			let days = match self.end {
+				Some(end) => (end.value.date() - self.start.value.date()).whole_days(),
+				None => self.duration.map_or(1, |x| x.whole_days()),
+			};
+			return (
+				begin,
+				Some(start.on(start.value.date() + Duration::days(days.max(1))).resolve()),
+			);
+		}
+		let length = match (self.end, self.duration) {
+			(Some(end), _) => end.resolve() - self.start.resolve(),
+			(None, Some(duration)) => duration,
+			(None, None) => Duration::ZERO,
+		};
+		(begin, (length > Duration::ZERO).then(|| begin + length))
+	}
+
+	/// Call `f` for every occurrence overlapping `from` to `to`, except those starting at `moved`.
+	fn expand(
+		&self,
+		from: OffsetDateTime,
+		to: OffsetDateTime,
+		moved: &[OffsetDateTime],
+		mut f: impl FnMut(OffsetDateTime, Option<OffsetDateTime>),
+	) {
+		let mut emit = |start: DateTime| {
+			let (begin, end) = self.occurrence(start);
+			let excluded = self.exdates.contains(&begin) || moved.contains(&begin);
+			if !excluded && begin < to && end.unwrap_or(begin) >= from {
+				f(begin, end);
+			}
+		};
+		let rule = match &self.rule {
+			Some(rule) => rule,
+			None => return emit(self.start),
+		};
+		let first = self.start.value.date();
+		let mut count = 0;
+		for period in 0..MAX_PERIODS {
+			let Some(dates) = rule.dates(first, period) else {
+				return;
+			};
+			for date in dates {
+				let start = self.start.on(date);
+				let past_until = rule.until.is_some_and(|until| {
+					if until.all_day {
+						date > until.value.date()
+					} else {
+						start.resolve() > until.resolve()
+					}
+				});
+				if rule.count.is_some_and(|x| count >= x) || past_until || start.resolve() >= to {
+					return;
+				}
+				count += 1;
+				emit(start);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_parse() {
+	let tz = raspi_lib::DEFAULT_TIMEZONE;
+	let text = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:standup\r
+SUMMARY:Stand-up\\, daily\r
+DTSTART;TZID=Europe/Berlin:20240429T093000\r
+DURATION:PT15M\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR;COUNT=6\r
+EXDATE;TZID=Europe/Berlin:20240503T093000\r
+BEGIN:VALARM\r
+TRIGGER:-PT5M\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup\r
+RECURRENCE-ID;TZID=Europe/Berlin:20240506T093000\r
+SUMMARY:Stand-up (moved)\r
+DTSTART:20240506T100000Z\r
+DTEND:20240506T101500Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Holiday\r
+DTSTART;VALUE=DATE:20240501\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Book club\r
+DTSTART;TZID=\"Europe/Berlin\":20240514T190000\r
+DTEND;TZID=\"Europe/Berlin\":20240514T210000\r
+RRULE:FREQ=MONTHLY;BYDAY=2TU\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Long\r
+ er name\r
+DTSTART:20240502T180000\r
+RRULE:FREQ=DAILY;UNTIL=20240503\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+	let from = raspi_lib::local_time(
+		Date::from_calendar_date(2024, Month::April, 29).unwrap(),
+		Time::MIDNIGHT,
+		tz,
+	);
+	let events = parse(text, tz, from, from + Duration::days(31)).unwrap();
+	assert_eq!(events[0].alert_minutes, Some(5));
+	assert_eq!(events[4].alert_minutes, None);
+	let events: Vec<_> = events
+		.iter()
+		.map(|x| (x.name.as_str(), x.start_time.as_str(), x.end_time.as_deref()))
+		.collect();
+	assert_eq!(
+		events,
+		[
+			("Stand-up, daily", "2024-04-29T09:30:00", Some("2024-04-29T09:45:00")),
+			("Stand-up, daily", "2024-05-01T09:30:00", Some("2024-05-01T09:45:00")),
+			("Stand-up, daily", "2024-05-08T09:30:00", Some("2024-05-08T09:45:00")),
+			("Stand-up, daily", "2024-05-10T09:30:00", Some("2024-05-10T09:45:00")),
+			("Stand-up (moved)", "2024-05-06T12:00:00", Some("2024-05-06T12:15:00")),
+			("Holiday", "2024-05-01T00:00:00", Some("2024-05-02T00:00:00")),
+			("Book club", "2024-05-14T19:00:00", Some("2024-05-14T21:00:00")),
+			("Longer name", "2024-05-02T18:00:00", None),
+			("Longer name", "2024-05-03T18:00:00", None),
+		]
+	);
+	assert!(matches!(
+		parse("BEGIN:VEVENT\nSUMMARY:x\nEND:VEVENT\n", tz, from, from),
+		Err(IcalError::MissingStart { line: 1 })
+	));
+	// intervals beyond the supported dates end the expansion
+	for rule in [
+		"DAILY;INTERVAL=4000000",
+		"WEEKLY;INTERVAL=4000000000",
+		"YEARLY;INTERVAL=4000000",
+	] {
+		let text = format!(
+			"BEGIN:VEVENT\nSUMMARY:x\nDTSTART:20240429T090000\nRRULE:FREQ={}\nEND:VEVENT\n",
+			rule
+		);
+		let events = parse(&text, tz, from, from + Duration::days(31)).unwrap();
+		assert_eq!(events.len(), 1, "{}", rule);
+	}
+}
@@ -0,0 +1,388 @@
+//! On-screen menu navigated with three buttons.
+//!
+//! The menu is a tree of [`MenuEntry`]s. Every level starts with an implicit entry
+//! that leaves the level (or closes the menu at the top level).
+//! While open, the [`Menu`] is the topmost draw of the [`ContextDefault`].
+
+use std::{
+	any::Any,
+	cell::{Cell, RefCell},
+	rc::Rc,
+	sync::atomic::Ordering,
+};
+
+use embedded_graphics::{
+	mono_font::{iso_8859_10::FONT_8X13, MonoTextStyle},
+	pixelcolor::Rgb565,
+	prelude::*,
+	primitives::Rectangle,
+	text::{Baseline, Text},
+};
+use raspi_lib::{Clock, Draw};
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+	action::Action,
+	context::{Context, ContextDefault, Rng, BLACK},
+	disable_pwm,
+	draw::{Agenda, MonthView, Totp},
+	enable_pwm, PWM_ON,
+};
+
+/// The menu closes if no button was pressed for this long.
+pub const TIMEOUT: Duration = Duration::seconds(10);
+
+/// Height of a menu line in pixels.
+const LINE_HEIGHT: i32 = 14;
+/// Number of lines, including the title.
+const LINES: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+	Up,
+	Down,
+	Select,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+	Screensaver(&'static str),
+	/// Close the screen below the menu.
+	CloseScreen,
+	/// Show the next page of the TOTP screen below the menu.
+	NextPage,
+}
+
+pub enum MenuEntry {
+	Action(String, MenuAction),
+	Submenu(String, Vec<MenuEntry>),
+	/// Boolean setting, flipped on select.
+	Toggle {
+		label: String,
+		get: fn() -> bool,
+		set: fn(bool),
+	},
+}
+
+impl MenuEntry {
+	fn label(&self) -> String {
+		match self {
+			MenuEntry::Action(label, _) | MenuEntry::Submenu(label, _) => label.clone(),
+			MenuEntry::Toggle { label, get, .. } => format!("[{}] {}", if get() { 'x' } else { ' ' }, label),
+		}
+	}
+}
+
+/// Result of a button press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuResult {
+	Stay,
+	Exit,
+	Run(MenuAction),
+}
+
+pub struct Menu {
+	entries: Vec<MenuEntry>,
+	/// Index of the opened submenu on each level.
+	submenus: RefCell<Vec<usize>>,
+	/// Selected line on the current level, 0 is the back/exit entry.
+	selected: Cell<usize>,
+	dirty: Cell<bool>,
+	clock: Rc<dyn Clock>,
+	last_input: Cell<OffsetDateTime>,
+}
+
+impl Menu {
+	pub fn new(entries: Vec<MenuEntry>, clock: Rc<dyn Clock>) -> Self {
+		let now = clock.now_utc();
+		Menu {
+			entries,
+			submenus: RefCell::new(vec![]),
+			selected: Cell::new(0),
+			dirty: Cell::new(true),
+			clock,
+			last_input: Cell::new(now),
+		}
+	}
+
+	/// The default menu: screensavers, closing the current screen, TOTP pages and the beeper.
+	pub fn main<D: DrawTarget<Color = Rgb565>>(ctx: &ContextDefault<D>) -> Self {
+		let screensavers = ctx
+			.screensaver_ids()
+			.map(|id| MenuEntry::Action(id.to_owned(), MenuAction::Screensaver(id)))
+			.collect();
+		let mut entries = vec![
+			MenuEntry::Submenu("Screens".to_owned(), screensavers),
+			MenuEntry::Action("Close screen".to_owned(), MenuAction::CloseScreen),
+		];
+		if ctx.screensaver_id("totp").is_some() {
+			entries.push(MenuEntry::Action("Next page".to_owned(), MenuAction::NextPage));
+		}
+		entries.push(MenuEntry::Toggle {
+			label: "Beep".to_owned(),
+			get: || PWM_ON.load(Ordering::Relaxed),
+			set: |on| {
+				let _ = if on { enable_pwm() } else { disable_pwm() };
+			},
+		});
+		Menu::new(entries, ctx.clock_handle())
+	}
+
+	fn current(&self) -> &[MenuEntry] {
+		let mut entries = &*self.entries;
+		for &idx in self.submenus.borrow().iter() {
+			if let MenuEntry::Submenu(_, sub) = &entries[idx] {
+				entries = sub;
+			}
+		}
+		entries
+	}
+
+	fn title(&self) -> String {
+		let mut entries = &*self.entries;
+		let mut title = "Menu".to_owned();
+		for &idx in self.submenus.borrow().iter() {
+			if let MenuEntry::Submenu(label, sub) = &entries[idx] {
+				title = label.clone();
+				entries = sub;
+			}
+		}
+		title
+	}
+
+	pub fn press(&self, button: Button) -> MenuResult {
+		self.last_input.set(self.clock.now_utc());
+		self.dirty.set(true);
+		let lines = self.current().len() + 1;
+		let selected = self.selected.get();
+		match button {
+			Button::Up => self.selected.set((selected + lines - 1) % lines),
+			Button::Down => self.selected.set((selected + 1) % lines),
+			Button::Select if selected == 0 => {
+				if self.submenus.borrow_mut().pop().is_none() {
+					return MenuResult::Exit;
+				}
+				self.selected.set(0);
+			},
+			Button::Select => match &self.current()[selected - 1] {
+				MenuEntry::Action(_, action) => return MenuResult::Run(*action),
+				MenuEntry::Submenu(..) => {
+					self.submenus.borrow_mut().push(selected - 1);
+					self.selected.set(0);
+				},
+				MenuEntry::Toggle { get, set, .. } => set(!get()),
+			},
+		}
+		MenuResult::Stay
+	}
+
+	pub fn is_stale(&self) -> bool {
+		self.clock.now_utc() - self.last_input.get() >= TIMEOUT
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Draw<D> for Menu {
+	fn draw(&self, disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
+		if !self.dirty.replace(false) {
+			return Ok(false);
+		}
+		disp.clear(BLACK)?;
+		let normal = MonoTextStyle::new(&FONT_8X13, Rgb565::WHITE);
+		let inverted = MonoTextStyle::new(&FONT_8X13, BLACK);
+		let title = MonoTextStyle::new(&FONT_8X13, Rgb565::YELLOW);
+		Text::with_baseline(&self.title(), Point::new(0, 0), title, Baseline::Top).draw(disp)?;
+
+		let back = if self.submenus.borrow().is_empty() {
+			"< Exit"
+		} else {
+			"< Back"
+		};
+		let labels: Vec<_> = std::iter::once(back.to_owned())
+			.chain(self.current().iter().map(MenuEntry::label))
+			.collect();
+		let visible = LINES - 1;
+		let selected = self.selected.get();
+		let first = selected.saturating_sub(visible - 1);
+		for (line, (idx, label)) in labels.iter().enumerate().skip(first).take(visible).enumerate() {
+			let y = (line as i32 + 1) * LINE_HEIGHT;
+			let style = if idx == selected {
+				disp.fill_solid(
+					&Rectangle::new(Point::new(0, y), Size::new(128, LINE_HEIGHT as u32)),
+					Rgb565::WHITE,
+				)?;
+				inverted
+			} else {
+				normal
+			};
+			Text::with_baseline(label, Point::new(2, y), style, Baseline::Top).draw(disp)?;
+		}
+		Ok(true)
+	}
+
+	fn redraw(&mut self) {
+		self.dirty.set(true);
+	}
+
+	fn priority(&self) -> u8 {
+		u8::MAX
+	}
+
+	fn name(&self) -> &'static str {
+		"menu"
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+fn top_menu<D: DrawTarget<Color = Rgb565>, T>(ctx: &ContextDefault<D>, f: impl FnOnce(&Menu) -> T) -> Option<T> {
+	let active = ctx.active.borrow();
+	let menu: Option<&Menu> = active.last()?.as_any().downcast_ref();
+	menu.map(f)
+}
+
+/// Turn the pages of the topmost screen with up and down. Returns whether it has pages.
+fn turn_page<D: DrawTarget<Color = Rgb565>>(ctx: &mut ContextDefault<D>, button: Button) -> bool {
+	let forward = match button {
+		Button::Up => false,
+		Button::Down => true,
+		Button::Select => return false,
+	};
+	let mut active = ctx.active.borrow_mut();
+	let Some(top) = active.last_mut() else {
+		return false;
+	};
+	if let Some(agenda) = top.as_any_mut().downcast_mut::<Agenda>() {
+		if forward {
+			agenda.next_page();
+		} else {
+			agenda.previous_page();
+		}
+	} else if let Some(month) = top.as_any_mut().downcast_mut::<MonthView>() {
+		if forward {
+			month.next_month();
+		} else {
+			month.previous_month();
+		}
+	} else {
+		return false;
+	}
+	true
+}
+
+/// Handle a button press: open the main menu or navigate the open one.
+/// Without a menu, up and down turn the pages of the [`Agenda`] and the [`MonthView`].
+pub fn press<D: DrawTarget<Color = Rgb565>>(ctx: &mut ContextDefault<D>, disp: &mut D, button: Button) {
+	let result = match top_menu(ctx, |menu| menu.press(button)) {
+		Some(result) => result,
+		None => {
+			if !turn_page(ctx, button) {
+				ctx.do_draw(Box::new(Menu::main(ctx)));
+			}
+			return;
+		},
+	};
+	match result {
+		MenuResult::Stay => {},
+		MenuResult::Exit => {
+			let _ = ctx.pop_action_and_clear(disp);
+		},
+		MenuResult::Run(action) => {
+			let _ = ctx.pop_action_and_clear(disp);
+			run(ctx, disp, action);
+		},
+	}
+}
+
+fn run<D: DrawTarget<Color = Rgb565>>(ctx: &mut ContextDefault<D>, disp: &mut D, action: MenuAction) {
+	match action {
+		MenuAction::Screensaver(id) => ctx.do_action(Action::Screensaver(id)),
+		MenuAction::CloseScreen => {
+			if ctx.active_count() > 1 {
+				let _ = ctx.pop_action_and_clear(disp);
+				let _ = disable_pwm();
+			}
+		},
+		MenuAction::NextPage => {
+			if let Some(x) = ctx.active.borrow_mut().last_mut() {
+				let totp: Option<&mut Totp> = x.as_any_mut().downcast_mut();
+				if let Some(x) = totp {
+					x.next_page();
+				}
+			}
+		},
+	}
+}
+
+/// Close the menu if it has not been used for [`TIMEOUT`].
+pub fn close_stale<D: DrawTarget<Color = Rgb565>>(ctx: &mut ContextDefault<D>, disp: &mut D) {
+	if top_menu(ctx, Menu::is_stale) == Some(true) {
+		let _ = ctx.pop_action_and_clear(disp);
+	}
+}
+
+#[test]
+fn test_menu() {
+	use crate::{config::Config, headless::Headless};
+
+	let config: Config =
+		toml::from_str("database = \":memory:\"\nscreensavers = [\"star\", \"rpi\"]\nschedule = []").unwrap();
+	let mut headless = Headless::new(&config, OffsetDateTime::UNIX_EPOCH, 1).unwrap();
+	let names =
+		|headless: &Headless| -> Vec<&'static str> { headless.ctx.active.borrow().iter().map(|x| x.name()).collect() };
+	let push = |headless: &mut Headless, button| {
+		press(&mut headless.ctx, &mut headless.disp, button);
+		headless.step();
+	};
+
+	// open, go to "Screens", select "rpi"
+	push(&mut headless, Button::Select);
+	assert_eq!(names(&headless), ["time", "menu"]);
+	push(&mut headless, Button::Down);
+	push(&mut headless, Button::Select);
+	push(&mut headless, Button::Up);
+	push(&mut headless, Button::Select);
+	assert_eq!(names(&headless), ["time", "rpi"]);
+
+	// close it again
+	push(&mut headless, Button::Select);
+	push(&mut headless, Button::Down);
+	push(&mut headless, Button::Down);
+	push(&mut headless, Button::Select);
+	assert_eq!(names(&headless), ["time"]);
+
+	// the menu closes by itself
+	push(&mut headless, Button::Select);
+	headless.clock.advance(TIMEOUT);
+	close_stale(&mut headless.ctx, &mut headless.disp);
+	assert_eq!(names(&headless), ["time"]);
+}
+
+#[test]
+fn test_menu_close_repaints() {
+	use crate::{config::Config, headless::Headless};
+	use raspi_lib::TextNotification;
+
+	let config: Config = toml::from_str("database = \":memory:\"\nscreensavers = []\nschedule = []").unwrap();
+	let mut headless = Headless::new(&config, OffsetDateTime::UNIX_EPOCH, 1).unwrap();
+	let lit = |headless: &Headless| headless.disp.panel().buffer.pixels().any(|x| x.0[..3] != [0, 0, 0]);
+	headless.ctx.do_draw(Box::new(TextNotification::new("Hello")));
+	assert!(headless.step());
+	press(&mut headless.ctx, &mut headless.disp, Button::Select);
+	assert!(headless.step());
+	// e.g. after a burn-in shift
+	headless.ctx.redraw(&mut headless.disp).unwrap();
+	assert!(headless.step());
+	assert!(lit(&headless));
+
+	// "< Exit" clears the display, the notification below has to paint itself again
+	press(&mut headless.ctx, &mut headless.disp, Button::Select);
+	assert!(!lit(&headless));
+	assert!(headless.step());
+	assert!(lit(&headless));
+}
@@ -0,0 +1,139 @@
+//! Brightness schedule of the display.
+
+use raspi_lib::{Display, DisplayError, Panel, Tz};
+use time::{Duration, OffsetDateTime, Time};
+use time_tz::OffsetDateTimeExt;
+
+use crate::context::{Context, ContextDefault};
+
+/// Dims or turns off the display depending on the time of day.
+pub struct NightMode {
+	/// Local time from which on a brightness applies, sorted by time.
+	levels: Vec<(Time, u8)>,
+	/// Brightness used for `wake_duration` after a button press while the display is off.
+	wake_level: u8,
+	wake_duration: Duration,
+	woken: Option<OffsetDateTime>,
+}
+
+impl NightMode {
+	/// `levels` are the local times at which the brightness changes; 0 turns the display off.
+	pub fn new(mut levels: Vec<(Time, u8)>, wake_level: u8, wake_duration: Duration) -> Self {
+		levels.sort();
+		NightMode {
+			levels,
+			wake_level,
+			wake_duration,
+			woken: None,
+		}
+	}
+
+	/// Scheduled brightness at local `time`, full brightness without a schedule.
+	pub fn scheduled(&self, time: Time) -> u8 {
+		// the last change of the previous day is still in effect before the first change
+		self.levels
+			.iter()
+			.rev()
+			.find(|x| x.0 <= time)
+			.or_else(|| self.levels.last())
+			.map_or(u8::MAX, |x| x.1)
+	}
+
+	pub fn brightness(&self, time: OffsetDateTime, tz: &Tz) -> u8 {
+		let level = self.scheduled(time.to_timezone(tz).time());
+		match self.woken {
+			Some(woken) if level == 0 && time - woken < self.wake_duration => self.wake_level,
+			_ => level,
+		}
+	}
+
+	/// Register a button press.
+	/// Returns `true` if the display was off, in which case the press should only wake it.
+	pub fn wake(&mut self, time: OffsetDateTime, tz: &Tz) -> bool {
+		let was_off = self.brightness(time, tz) == 0;
+		self.woken = Some(time);
+		was_off
+	}
+
+	/// Apply the brightness at `time` to `disp`, drawing the active screen again if needed.
+	/// Returns whether the frame changed.
+	pub fn apply<P: Panel>(
+		&self,
+		ctx: &mut ContextDefault<Display<P>>,
+		disp: &mut Display<P>,
+		time: OffsetDateTime,
+	) -> Result<bool, DisplayError> {
+		let level = self.brightness(time, ctx.timezone());
+		let asleep = disp.is_asleep();
+		let mut redraw = disp.set_sleep(level == 0)?;
+		if level != 0 {
+			redraw |= disp.set_brightness(level)?;
+		}
+		if redraw {
+			let _ = ctx.redraw(disp);
+		}
+		Ok(redraw || asleep != disp.is_asleep())
+	}
+}
+
+impl Default for NightMode {
+	fn default() -> Self {
+		NightMode::new(vec![], u8::MAX, Duration::ZERO)
+	}
+}
+
+#[test]
+fn test_night_mode() {
+	use crate::{config::Config, headless::Headless};
+
+	let config: Config = toml::from_str(
+		r#"
+		database = ":memory:"
+		screensavers = []
+		schedule = []
+
+		[brightness]
+		wake_secs = 30
+		wake_level = 100
+		schedule = [
+			{ time = "07:00", level = 255 },
+			{ time = "22:00", level = 64 },
+			{ time = "00:00", level = 0 },
+		]
+		"#,
+	)
+	.unwrap();
+	let night = config.night_mode().unwrap();
+	let hms = |h, m| Time::from_hms(h, m, 0).unwrap();
+	assert_eq!(night.scheduled(hms(12, 0)), 255);
+	assert_eq!(night.scheduled(hms(23, 0)), 64);
+	assert_eq!(night.scheduled(hms(3, 0)), 0);
+	assert_eq!(NightMode::default().scheduled(hms(3, 0)), 255);
+
+	let tz = config.timezone().unwrap();
+	let date = time::Date::from_calendar_date(2024, time::Month::May, 3).unwrap();
+	let mut headless = Headless::new(&config, raspi_lib::local_time(date, hms(21, 59), tz), 1).unwrap();
+	headless.step();
+	let lit = headless.disp.panel().buffer.clone();
+	headless.clock.set(raspi_lib::local_time(date, hms(22, 0), tz));
+	headless.step();
+	assert_eq!(headless.disp.brightness(), 64);
+	let dimmed = headless.disp.panel().buffer.clone();
+	let sum = |x: &[u8]| {
+		x.chunks(4)
+			.map(|x| x[..3].iter().map(|&x| x as u64).sum::<u64>())
+			.sum::<u64>()
+	};
+	assert!(sum(&dimmed) < sum(&lit) / 2, "{} {}", sum(&dimmed), sum(&lit));
+
+	let midnight = raspi_lib::local_time(date.next_day().unwrap(), hms(0, 30), tz);
+	headless.clock.set(midnight);
+	headless.step();
+	assert!(headless.disp.is_asleep());
+	assert!(!headless.press(crate::menu::Button::Select));
+	assert!(!headless.disp.is_asleep());
+	assert_eq!(headless.disp.brightness(), 100);
+	headless.clock.set(midnight + Duration::seconds(31));
+	headless.step();
+	assert!(headless.disp.is_asleep());
+}
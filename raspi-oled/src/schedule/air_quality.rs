@@ -0,0 +1,184 @@
+use std::{
+	any::Any,
+	cell::{Cell, RefCell},
+};
+
+use embedded_graphics::{
+	mono_font::{ascii::FONT_6X9, MonoTextStyle},
+	pixelcolor::Rgb565,
+	prelude::{DrawTarget, Point, RgbColor},
+	text::{Baseline, Text},
+	Drawable,
+};
+use raspi_lib::{Draw, FONT_10X20};
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+	context::{Context, Rng, BLACK},
+	storage::{Quality, Quantity},
+};
+
+use super::Schedule;
+
+/// Readings older than this are ignored.
+const MAX_AGE: Duration = Duration::minutes(10);
+/// Frames per flash phase (about half a second).
+const FLASH_FRAMES: u32 = 8;
+/// Frames until the alert closes by itself (about a minute).
+const ALERT_FRAMES: u32 = 900;
+
+/// Ask to open the window when eCO2 reaches `threshold`.
+/// The alert is shown again only after eCO2 dropped below `threshold - hysteresis`.
+pub struct AirQualityAlert {
+	pub sensor: String,
+	/// eCO2 in ppm.
+	pub threshold: u16,
+	pub hysteresis: u16,
+	pub beep: bool,
+	/// Set when the alert was shown, until the air is good again.
+	pub alerted: Cell<bool>,
+	pub last_check: RefCell<Option<OffsetDateTime>>,
+}
+
+impl AirQualityAlert {
+	pub fn new(sensor: impl Into<String>, threshold: u16, hysteresis: u16, beep: bool) -> Self {
+		AirQualityAlert {
+			sensor: sensor.into(),
+			threshold,
+			hysteresis,
+			beep,
+			alerted: Cell::new(false),
+			last_check: RefCell::new(None),
+		}
+	}
+
+	fn latest_eco2<D: DrawTarget<Color = Rgb565>>(&self, ctx: &dyn Context<D>, time: OffsetDateTime) -> Option<u16> {
+		let latest = ctx.database().latest(&self.sensor, Quantity::Eco2).ok()??;
+		if latest.quality != Quality::Good || time - latest.time > MAX_AGE {
+			return None;
+		}
+		Some(latest.value as u16)
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Schedule<D> for AirQualityAlert {
+	fn check(&self, ctx: &dyn Context<D>, time: OffsetDateTime) -> bool {
+		let mut last_check = self.last_check.borrow_mut();
+		if last_check.is_some_and(|x| time - x < Duration::minutes(1)) {
+			return false;
+		}
+		*last_check = Some(time);
+		let Some(eco2) = self.latest_eco2(ctx, time) else {
+			return false;
+		};
+		if self.alerted.get() {
+			if eco2 < self.threshold.saturating_sub(self.hysteresis) {
+				self.alerted.set(false);
+			}
+			return false;
+		}
+		eco2 >= self.threshold
+	}
+
+	fn execute(&self, ctx: &dyn Context<D>, time: OffsetDateTime) {
+		self.alerted.set(true);
+		let eco2 = self.latest_eco2(ctx, time).unwrap_or(self.threshold);
+		if self.beep {
+			ctx.enable_pwm();
+		}
+		ctx.do_draw(Box::new(OpenWindow {
+			eco2,
+			calls: Cell::new(0),
+		}));
+	}
+}
+
+/// Flashing "open the window" screen.
+pub struct OpenWindow {
+	eco2: u16,
+	calls: Cell<u32>,
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Draw<D> for OpenWindow {
+	fn draw(&self, disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
+		let calls = self.calls.get();
+		self.calls.set(calls + 1);
+		if !calls.is_multiple_of(FLASH_FRAMES) {
+			return Ok(false);
+		}
+		let (background, foreground) = if (calls / FLASH_FRAMES).is_multiple_of(2) {
+			(Rgb565::RED, Rgb565::WHITE)
+		} else {
+			(BLACK, Rgb565::RED)
+		};
+		disp.clear(background)?;
+		let large = MonoTextStyle::new(&FONT_10X20, foreground);
+		let small = MonoTextStyle::new(&FONT_6X9, foreground);
+		Text::with_baseline("Open the", Point::new(24, 30), large, Baseline::Top).draw(disp)?;
+		Text::with_baseline("window!", Point::new(29, 52), large, Baseline::Top).draw(disp)?;
+		let text = format!("eCO2 {} ppm", self.eco2);
+		let x = (128 - 6 * text.len() as i32) / 2;
+		Text::with_baseline(&text, Point::new(x, 90), small, Baseline::Top).draw(disp)?;
+		Ok(true)
+	}
+
+	fn expired(&self) -> bool {
+		self.calls.get() > ALERT_FRAMES
+	}
+
+	fn priority(&self) -> u8 {
+		1
+	}
+
+	fn name(&self) -> &'static str {
+		"open_window"
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+#[test]
+fn test_air_quality_alert() {
+	use crate::{config::Config, headless::Headless, storage::AIR_SENSOR};
+
+	let config: Config = toml::from_str(
+		r#"
+		database = ":memory:"
+		screensavers = []
+
+		[[schedule]]
+		type = "air_quality"
+		threshold = 1500
+		"#,
+	)
+	.unwrap();
+	let start = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+	let mut headless = Headless::new(&config, start, 1).unwrap();
+	let measure = |headless: &mut Headless, eco2: f64| {
+		let time = headless.ctx.now() + Duration::minutes(1);
+		headless.clock.set(time);
+		let storage = headless.ctx.database();
+		storage
+			.insert(time, AIR_SENSOR, Quantity::Eco2, eco2, Quality::Good)
+			.unwrap();
+		headless.step();
+		let names: Vec<_> = headless.ctx.active.borrow().iter().map(|x| x.name()).collect();
+		names
+	};
+
+	assert_eq!(measure(&mut headless, 1200.0), ["time"]);
+	assert_eq!(measure(&mut headless, 1600.0), ["time", "open_window"]);
+	headless.run(ALERT_FRAMES as usize + 2, |_, _, _| {});
+	assert_eq!(headless.ctx.active_count(), 1);
+	// still above threshold - hysteresis: no new alert
+	assert_eq!(measure(&mut headless, 1400.0), ["time"]);
+	assert_eq!(measure(&mut headless, 1550.0), ["time"]);
+	assert_eq!(measure(&mut headless, 1000.0), ["time"]);
+	assert_eq!(measure(&mut headless, 1550.0), ["time", "open_window"]);
+}
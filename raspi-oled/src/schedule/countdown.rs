@@ -0,0 +1,270 @@
+use std::{
+	any::Any,
+	cell::{Cell, RefCell},
+	path::PathBuf,
+	rc::Rc,
+};
+
+use embedded_graphics::{
+	mono_font::{ascii::FONT_6X9, MonoTextStyle},
+	pixelcolor::Rgb565,
+	prelude::{DrawTarget, Point, RgbColor},
+	text::{Baseline, Text},
+	Drawable,
+};
+use raspi_lib::{Clock, Draw, FONT_10X20};
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+	context::{Context, Rng, BLACK},
+	events::{Events, Occurrence, Vacation},
+};
+
+use super::Schedule;
+
+/// Lines of the event name shown by the countdown.
+const NAME_LINES: usize = 3;
+
+/// Show a [`Countdown`] some minutes before each event in the events file.
+pub struct CountdownAlert {
+	pub events: PathBuf,
+	pub vacations: Vec<Vacation>,
+	/// Lead time of events without their own, 0 disables their countdown.
+	pub minutes: u32,
+	pub beep: bool,
+	/// Name and start of the occurrences already counted down.
+	pub alerted: RefCell<Vec<(String, OffsetDateTime)>>,
+	pub last_check: RefCell<Option<OffsetDateTime>>,
+	/// Found by `check`, shown by `execute`.
+	pub due: RefCell<Option<Occurrence>>,
+}
+
+impl CountdownAlert {
+	pub fn new(events: impl Into<PathBuf>, vacations: Vec<Vacation>, minutes: u32, beep: bool) -> Self {
+		CountdownAlert {
+			events: events.into(),
+			vacations,
+			minutes,
+			beep,
+			alerted: RefCell::new(vec![]),
+			last_check: RefCell::new(None),
+			due: RefCell::new(None),
+		}
+	}
+
+	fn is_due(&self, occurrence: &Occurrence, time: OffsetDateTime) -> bool {
+		let lead = occurrence
+			.alert
+			.unwrap_or_else(|| Duration::minutes(self.minutes as i64));
+		lead > Duration::ZERO && occurrence.start - lead <= time && time < occurrence.start
+	}
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Schedule<D> for CountdownAlert {
+	fn check(&self, ctx: &dyn Context<D>, time: OffsetDateTime) -> bool {
+		let mut last_check = self.last_check.borrow_mut();
+		if last_check.is_some_and(|x| time - x < Duration::minutes(1)) {
+			return false;
+		}
+		*last_check = Some(time);
+		// load errors are reported by the calendar view
+		let Ok(mut events) = Events::load(&self.events, ctx.timezone(), time) else {
+			return false;
+		};
+		events.vacations = self.vacations.clone();
+		let mut alerted = self.alerted.borrow_mut();
+		alerted.retain(|x| x.1 > time);
+		let due = events
+			.upcoming(time, 1, ctx.timezone())
+			.occurrences
+			.into_iter()
+			.filter(|x| self.is_due(x, time))
+			.find(|x| !alerted.iter().any(|(name, start)| *name == x.name && *start == x.start));
+		let found = due.is_some();
+		*self.due.borrow_mut() = due;
+		found
+	}
+
+	fn execute(&self, ctx: &dyn Context<D>, _time: OffsetDateTime) {
+		let Some(occurrence) = self.due.borrow_mut().take() else {
+			return;
+		};
+		self.alerted
+			.borrow_mut()
+			.push((occurrence.name.clone(), occurrence.start));
+		if self.beep {
+			ctx.enable_pwm();
+		}
+		ctx.do_draw(Box::new(Countdown {
+			name: occurrence.name,
+			start: occurrence.start,
+			clock: ctx.clock_handle(),
+			shown: Cell::new(None),
+		}));
+	}
+}
+
+/// Name of the next event and the time until it starts.
+pub struct Countdown {
+	name: String,
+	start: OffsetDateTime,
+	clock: Rc<dyn Clock>,
+	/// Seconds left on the last drawn frame.
+	shown: Cell<Option<i64>>,
+}
+
+impl<D: DrawTarget<Color = Rgb565>> Draw<D> for Countdown {
+	fn draw(&self, disp: &mut D, _rng: &mut Rng) -> Result<bool, D::Error> {
+		let left = (self.start - self.clock.now_utc()).whole_seconds().max(0);
+		if self.shown.get() == Some(left) {
+			return Ok(false);
+		}
+		self.shown.set(Some(left));
+		disp.clear(BLACK)?;
+		let small = MonoTextStyle::new(&FONT_6X9, Rgb565::WHITE);
+		let large = MonoTextStyle::new(&FONT_10X20, Rgb565::WHITE);
+		Text::with_baseline("Starting soon", Point::new(25, 4), small, Baseline::Top).draw(disp)?;
+		for (idx, line) in raspi_lib::wrap(&self.name, 12).iter().take(NAME_LINES).enumerate() {
+			let x = (128 - 10 * line.chars().count() as i32) / 2;
+			Text::with_baseline(line, Point::new(x, 20 + 20 * idx as i32), large, Baseline::Top).draw(disp)?;
+		}
+		let text = if left >= 3600 {
+			format!("{}:{:02}:{:02}", left / 3600, left / 60 % 60, left % 60)
+		} else {
+			format!("{:02}:{:02}", left / 60, left % 60)
+		};
+		let x = (128 - 10 * text.len() as i32) / 2;
+		let countdown = MonoTextStyle::new(&FONT_10X20, Rgb565::YELLOW);
+		Text::with_baseline(&text, Point::new(x, 96), countdown, Baseline::Top).draw(disp)?;
+		Ok(true)
+	}
+
+	fn expired(&self) -> bool {
+		self.clock.now_utc() >= self.start
+	}
+
+	fn redraw(&mut self) {
+		self.shown.set(None);
+	}
+
+	fn priority(&self) -> u8 {
+		1
+	}
+
+	fn name(&self) -> &'static str {
+		"countdown"
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+}
+
+#[test]
+fn test_countdown() {
+	use crate::{config::Config, headless::Headless};
+	use time::{Date, Month, Time};
+
+	let path = std::env::temp_dir().join(format!("raspi-oled-countdown-{}.json", std::process::id()));
+	std::fs::write(
+		&path,
+		r#"{
+			"events": [
+				{"name": "Dentist", "startTime": "2024-05-03T10:00:00"},
+				{"name": "Call", "startTime": "2024-05-03T10:30:00", "alertMinutes": 2},
+				{"name": "Lunch", "startTime": "2024-05-03T12:00:00", "alertMinutes": 0}
+			],
+			"weekly": []
+		}"#,
+	)
+	.unwrap();
+	let config: Config = toml::from_str(&format!(
+		r#"
+		database = ":memory:"
+		screensavers = []
+		events = {:?}
+
+		[[schedule]]
+		type = "countdown"
+		minutes = 10
+		"#,
+		path.display().to_string()
+	))
+	.unwrap();
+	let tz = config.timezone().unwrap();
+	let friday = Date::from_calendar_date(2024, Month::May, 3).unwrap();
+	let at = |hour, minute| raspi_lib::local_time(friday, Time::from_hms(hour, minute, 0).unwrap(), tz);
+	let mut headless = Headless::new(&config, at(9, 49), 1).unwrap();
+	let mut names_at = |hour, minute| {
+		headless.clock.set(at(hour, minute));
+		headless.step();
+		let names: Vec<_> = headless.ctx.active.borrow().iter().map(|x| x.name()).collect();
+		names
+	};
+
+	assert_eq!(names_at(9, 49), ["time"]);
+	assert_eq!(names_at(9, 50), ["time", "countdown"]);
+	assert_eq!(names_at(9, 58), ["time", "countdown"]);
+	// expires when the event starts and is not shown again
+	assert_eq!(names_at(10, 0), ["time"]);
+	assert_eq!(names_at(10, 1), ["time"]);
+	assert_eq!(names_at(10, 27), ["time"]);
+	assert_eq!(names_at(10, 28), ["time", "countdown"]);
+	assert_eq!(names_at(10, 30), ["time"]);
+	assert_eq!(names_at(11, 55), ["time"]);
+	std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_countdown_expiry_repaints() {
+	use crate::{action::Action, config::Config, headless::Headless};
+	use time::{Date, Month, Time};
+
+	let path = std::env::temp_dir().join(format!("raspi-oled-countdown-repaint-{}.json", std::process::id()));
+	std::fs::write(
+		&path,
+		r#"{"events": [{"name": "Dentist", "startTime": "2024-05-03T10:00:00"}], "weekly": []}"#,
+	)
+	.unwrap();
+	let config: Config = toml::from_str(&format!(
+		r#"
+		database = ":memory:"
+		screensavers = ["measurements"]
+		events = {:?}
+
+		[[schedule]]
+		type = "countdown"
+
+		# moving the screen would repaint it as well
+		[burn_in.default]
+		shift = 0
+		"#,
+		path.display().to_string()
+	))
+	.unwrap();
+	let tz = config.timezone().unwrap();
+	let friday = Date::from_calendar_date(2024, Month::May, 3).unwrap();
+	let at = |hour, minute| raspi_lib::local_time(friday, Time::from_hms(hour, minute, 0).unwrap(), tz);
+	let mut headless = Headless::new(&config, at(9, 45), 1).unwrap();
+	headless.do_action(Action::Screensaver("measurements"));
+	headless.step();
+	headless.clock.set(at(9, 55));
+	headless.step();
+	let names =
+		|headless: &Headless| -> Vec<&'static str> { headless.ctx.active.borrow().iter().map(|x| x.name()).collect() };
+	assert_eq!(names(&headless), ["time", "measurements", "countdown"]);
+
+	// the measurements come back as if they had never been covered
+	headless.clock.set(at(10, 0));
+	assert!(headless.step());
+	assert_eq!(names(&headless), ["time", "measurements"]);
+	let mut fresh = Headless::new(&config, at(10, 0), 1).unwrap();
+	fresh.do_action(Action::Screensaver("measurements"));
+	fresh.step();
+	assert!(headless.disp.panel().buffer == fresh.disp.panel().buffer);
+	std::fs::remove_file(&path).unwrap();
+}
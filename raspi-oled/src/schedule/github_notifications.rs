@@ -2,15 +2,13 @@ use std::{cell::RefCell, collections::HashSet};
 
 use color_space::{Hsv, ToRgb};
 use embedded_graphics::{
-	mono_font::{iso_8859_10::FONT_8X13, MonoTextStyleBuilder},
 	pixelcolor::Rgb565,
 	prelude::{DrawTarget, Point, RgbColor},
 	primitives::Rectangle,
-	text::Text,
-	Drawable, Pixel,
+	Pixel,
 };
 use rand_xoshiro::rand_core::RngCore;
-use raspi_lib::Draw;
+use raspi_lib::{Draw, TextNotification};
 
 use crate::{
 	context::{Context, Rng},
@@ -23,17 +21,23 @@ use super::Schedule;
 pub struct GithubNotifications {
 	pub pat: String,
 	pub last_modified: RefCell<Option<String>>,
-	pub last_call: RefCell<time::OffsetDateTime>,
+	/// Time of the last API call, `None` before the first check.
+	pub last_call: RefCell<Option<time::OffsetDateTime>>,
 }
 
 impl<D: DrawTarget<Color = Rgb565>> Schedule<D> for GithubNotifications {
 	fn check(&self, _ctx: &dyn Context<D>, time: time::OffsetDateTime) -> bool {
-		let time_since_last = time - *self.last_call.borrow();
+		// first call shortly after startup
+		let last_call = *self
+			.last_call
+			.borrow_mut()
+			.get_or_insert(time - time::Duration::seconds(50));
+		let time_since_last = time - last_call;
 		time_since_last.whole_minutes() >= 1
 	}
 
 	fn execute(&self, ctx: &dyn Context<D>, time: time::OffsetDateTime) {
-		*self.last_call.borrow_mut() = time;
+		*self.last_call.borrow_mut() = Some(time);
 		let last_modified = self.last_modified.borrow().clone();
 		let new = get_new_notifications(&self.pat, last_modified.as_deref());
 		if let Ok((notifications, last_modified)) = new {
@@ -75,7 +79,7 @@ impl<D: DrawTarget<Color = Rgb565>> Schedule<D> for GithubNotifications {
 			ctx.do_draw(Box::new(GithubNotificationsDraw {
 				calls: RefCell::new(0),
 				screen: &GITHUB,
-				lines,
+				text: TextNotification::new(lines.join("\n")).without_wrap(),
 				circles: RefCell::new(vec![]),
 			}));
 		} else {
@@ -87,7 +91,7 @@ impl<D: DrawTarget<Color = Rgb565>> Schedule<D> for GithubNotifications {
 struct GithubNotificationsDraw {
 	calls: RefCell<usize>,
 	screen: &'static SimpleScreensaver,
-	lines: Vec<String>,
+	text: TextNotification,
 	circles: RefCell<Vec<((u32, u32), u32, Rgb565, Vec<(u32, u32)>)>>,
 }
 
@@ -167,30 +171,8 @@ impl<D: DrawTarget<Color = Rgb565>> Draw<D> for GithubNotificationsDraw {
 			circles.retain(|x| x.1 < 10);
 			circles.push(((x, y), 0, rgb, vec![(x, y)]));
 		} else {
-			let idx = calls - 70;
-			disp.clear(Rgb565::BLACK)?;
-			// fit 9 lines
-			let max_line_length = 16;
-			let text_style_clock = MonoTextStyleBuilder::new()
-				.font(&FONT_8X13)
-				.text_color(Rgb565::WHITE)
-				.build();
-			for (y, line) in self.lines.iter().enumerate() {
-				let mut line = if calls >= 119 {
-					if y % 2 == 0 {
-						line.clone()
-					} else {
-						format!(" {line}")
-					}
-				} else if line.len() > max_line_length {
-					let line = format!("               {line} ");
-					line[line.ceil_char_boundary(idx % line.len())..].to_string()
-				} else {
-					line.clone()
-				};
-				line.truncate(line.floor_char_boundary(max_line_length));
-				Text::new(&line, Point::new(0, (12 + y * 14) as _), text_style_clock).draw(disp)?;
-			}
+			*self.calls.borrow_mut() += 1;
+			return self.text.draw(disp, rng);
 		}
 		*self.calls.borrow_mut() += 1;
 		Ok(calls < 120)
@@ -200,6 +182,14 @@ impl<D: DrawTarget<Color = Rgb565>> Draw<D> for GithubNotificationsDraw {
 		*self.calls.borrow() > 140
 	}
 
+	fn redraw(&mut self) {
+		Draw::<D>::redraw(&mut self.text);
+	}
+
+	fn name(&self) -> &'static str {
+		"github_notifications"
+	}
+
 	fn as_any(&self) -> &dyn std::any::Any {
 		&*self
 	}
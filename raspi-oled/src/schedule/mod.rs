@@ -1,8 +1,12 @@
+use std::cell::Cell;
+
 use embedded_graphics::{pixelcolor::Rgb565, prelude::DrawTarget};
-use time::OffsetDateTime;
+use time::{Duration, OffsetDateTime, Time};
 
 use crate::{action::Action, context::Context};
 
+pub mod air_quality;
+pub mod countdown;
 pub mod github_notifications;
 
 /// Task to be executed at certain times.
@@ -21,20 +25,32 @@ where
 	fn execute(&self, ctx: &dyn Context<D>, time: OffsetDateTime);
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Reminder {
 	hour: u8,
 	minute: u8,
 	action: Action,
 	should_beep: bool,
+	/// Julian day of the last execution.
+	last_day: Cell<i32>,
 }
 
 impl<D: DrawTarget<Color = Rgb565>> Schedule<D> for Reminder {
 	fn check(&self, ctx: &dyn Context<D>, time: OffsetDateTime) -> bool {
-		time.hour() == self.hour && time.minute() == self.minute && ctx.active_count() == 1
+		if ctx.active_count() != 1 || self.last_day.get() == time.to_julian_day() {
+			return false;
+		}
+		// resolve the wall clock time on this day to handle DST transitions
+		let Ok(due) = Time::from_hms(self.hour, self.minute, 0) else {
+			return false;
+		};
+		let due = raspi_lib::local_time(time.date(), due, ctx.timezone());
+		let since_due = time - due;
+		since_due >= Duration::ZERO && since_due < Duration::minutes(1)
 	}
 
-	fn execute(&self, ctx: &dyn Context<D>, _time: OffsetDateTime) {
+	fn execute(&self, ctx: &dyn Context<D>, time: OffsetDateTime) {
+		self.last_day.set(time.to_julian_day());
 		if self.should_beep {
 			ctx.enable_pwm();
 		}
@@ -43,20 +59,91 @@ impl<D: DrawTarget<Color = Rgb565>> Schedule<D> for Reminder {
 }
 
 impl Reminder {
-	const fn new(hour: u8, minute: u8, action: Action, should_beep: bool) -> Self {
+	pub const fn new(hour: u8, minute: u8, action: Action, should_beep: bool) -> Self {
 		Reminder {
 			hour,
 			minute,
 			action,
 			should_beep,
+			last_day: Cell::new(0),
 		}
 	}
 }
 
-static DUOLINGO: Reminder = Reminder::new(11, 40, Action::Screensaver("duolingo"), false);
-static DUOLINGO_NIGHT: Reminder = Reminder::new(23, 40, Action::Screensaver("duolingo"), false);
-static FOOD: Reminder = Reminder::new(13, 15, Action::Screensaver("plate"), false);
+#[test]
+fn test_friday_evening() {
+	use crate::{config::Config, headless::Headless};
+	use time::{Date, Month};
+
+	let config: Config = toml::from_str(
+		r#"
+		database = ":memory:"
+		screensavers = ["duolingo"]
 
-pub fn reminders<D: DrawTarget<Color = Rgb565>>() -> Vec<Box<dyn Schedule<D>>> {
-	vec![Box::new(DUOLINGO), Box::new(DUOLINGO_NIGHT), Box::new(FOOD)]
+		[[schedule]]
+		type = "reminder"
+		time = "23:40"
+		action = "duolingo"
+
+		[[schedule]]
+		type = "bear"
+		"#,
+	)
+	.unwrap();
+	let tz = config.timezone().unwrap();
+	let friday = Date::from_calendar_date(2024, Month::May, 3).unwrap();
+	let at = |hour, minute| raspi_lib::local_time(friday, Time::from_hms(hour, minute, 0).unwrap(), tz);
+
+	let mut headless = Headless::new(&config, at(19, 59), 1).unwrap();
+	headless.step();
+	assert_eq!(headless.ctx.active_count(), 1);
+	headless.clock.set(at(20, 0));
+	headless.step();
+	assert_eq!(headless.ctx.active_count(), 2);
+	// the bear animation expires and is not shown again on the same day
+	headless.run(120, |_, _, _| {});
+	assert_eq!(headless.ctx.active_count(), 1);
+	headless.clock.set(at(20, 30));
+	headless.step();
+	assert_eq!(headless.ctx.active_count(), 1);
+	headless.clock.set(at(23, 40));
+	headless.step();
+	assert_eq!(headless.ctx.active_count(), 2);
+}
+
+#[test]
+fn test_reminder_dst() {
+	use crate::{config::Config, headless::Headless};
+	use time::{Date, Month};
+
+	let config: Config = toml::from_str(
+		r#"
+		database = ":memory:"
+		screensavers = ["duolingo"]
+		timezone = "Europe/Berlin"
+
+		[[schedule]]
+		type = "reminder"
+		time = "02:30"
+		action = "duolingo"
+		"#,
+	)
+	.unwrap();
+	let tz = config.timezone().unwrap();
+	// 02:30 does not exist on the first day and happens twice on the second
+	for (month, day) in [(Month::March, 31), (Month::October, 27)] {
+		let date = Date::from_calendar_date(2024, month, day).unwrap();
+		let midnight = raspi_lib::local_time(date, Time::MIDNIGHT, tz);
+		let mut headless = Headless::new(&config, midnight, 1).unwrap();
+		let mut fired = 0;
+		for idx in 0..6 * 60 * 3 {
+			headless.clock.set(midnight + Duration::seconds(20 * idx));
+			headless.step();
+			if headless.ctx.active_count() == 2 {
+				fired += 1;
+				headless.ctx.pop_action_and_clear(&mut headless.disp).unwrap();
+			}
+		}
+		assert_eq!(fired, 1, "{}", date);
+	}
 }
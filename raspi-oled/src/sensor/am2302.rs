@@ -0,0 +1,261 @@
+//! AM2302 (DHT22) temperature and humidity sensor on a single GPIO line.
+
+use std::{
+	thread::sleep,
+	time::{Duration, Instant},
+};
+
+use gpiocdev::{
+	line::{Bias, EdgeDetection, EdgeKind, Value},
+	request::Config,
+	Request,
+};
+
+use super::{Reading, Sensor, SensorError};
+
+/// How the edges of the sensor response are recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeMode {
+	/// Busy-poll the line value under `SCHED_FIFO`.
+	Poll,
+	/// Use kernel edge events with their timestamps.
+	Events,
+}
+
+pub struct Am2302 {
+	pub chip: String,
+	pub line: u32,
+	pub mode: EdgeMode,
+}
+
+/// Edges of a complete response: start (3 edges), 40 bits (2 each), end of the last bit.
+const RESPONSE_EDGES: usize = 83;
+/// High pulses longer than this (in µs) are 1 bits.
+const ONE_THRESHOLD: u64 = 35;
+const MAX_HUMIDITY: u16 = 1000;
+
+impl Am2302 {
+	pub fn new(chip: impl Into<String>, line: u32) -> Self {
+		Am2302 {
+			chip: chip.into(),
+			line,
+			mode: EdgeMode::Poll,
+		}
+	}
+
+	pub fn with_mode(mut self, mode: EdgeMode) -> Self {
+		self.mode = mode;
+		self
+	}
+
+	/// Send the start signal and record the edges of the response.
+	pub fn record(&self) -> Result<Vec<(u64, EdgeKind)>, SensorError> {
+		let out = gpiocdev::Request::builder()
+			.on_chip(&self.chip)
+			.with_line(self.line)
+			.as_output(Value::Active)
+			.request()?;
+		out.set_value(self.line, Value::Active)?;
+		sleep(Duration::from_millis(500));
+		set_max_priority();
+		out.set_value(self.line, Value::Inactive)?;
+		sleep(Duration::from_millis(2));
+
+		let edges = match self.mode {
+			EdgeMode::Poll => poll_edges(Duration::from_secs(1), out, self.line),
+			EdgeMode::Events => edge_events(Duration::from_secs(1), out),
+		};
+		set_normal_priority();
+		edges
+	}
+}
+
+impl Default for Am2302 {
+	/// Line 26 of the Raspberry Pi GPIO header.
+	fn default() -> Self {
+		Am2302::new("/dev/gpiochip0", 26)
+	}
+}
+
+impl Sensor for Am2302 {
+	fn read(&mut self) -> Result<Reading, SensorError> {
+		let (humidity, celsius) = decode(&self.record()?)?;
+		Ok(Reading {
+			humidity: Some(humidity),
+			celsius: Some(celsius),
+			..Reading::default()
+		})
+	}
+}
+
+/// Decode recorded edges (timestamp in µs, kind) into humidity (0.1 %) and temperature (0.1 °C).
+pub fn decode(edges: &[(u64, EdgeKind)]) -> Result<(u16, i16), SensorError> {
+	let bits: Vec<u8> = edges
+		.windows(2)
+		.filter(|pair| pair[0].1 == EdgeKind::Rising && pair[1].1 == EdgeKind::Falling)
+		.map(|pair| (pair[1].0 - pair[0].0 > ONE_THRESHOLD) as u8)
+		.collect();
+	decode_bits(&bits)
+}
+
+/// Decode the high pulses of a response, optionally including the 80 µs pulse that precedes the data.
+fn decode_bits(bits: &[u8]) -> Result<(u16, i16), SensorError> {
+	let bits = if bits.len() > 40 {
+		&bits[bits.len() - 40..]
+	} else if bits.len() == 40 && bits[0] == 1 {
+		// the humidity never sets the highest bit, so this is the response pulse
+		return Err(SensorError::MissingBits { bits: 39 });
+	} else if bits.len() < 40 {
+		return Err(SensorError::MissingBits { bits: bits.len() });
+	} else {
+		bits
+	};
+	let bytes: Vec<u8> = bits
+		.chunks(8)
+		.map(|chunk| {
+			chunk
+				.iter()
+				.enumerate()
+				// 8 bits, starting with the MSB
+				.map(|(bit_idx, &x)| x << (7 - bit_idx))
+				.sum()
+		})
+		.collect();
+	let checksum = bytes[0]
+		.wrapping_add(bytes[1])
+		.wrapping_add(bytes[2])
+		.wrapping_add(bytes[3]);
+	if checksum != bytes[4] {
+		return Err(SensorError::ChecksumMismatch {
+			expected: bytes[4],
+			actual: checksum,
+		});
+	}
+	let rh = (bytes[0] as u16) << 8 | bytes[1] as u16;
+	if rh > MAX_HUMIDITY {
+		return Err(SensorError::HumidityTooHigh(rh));
+	}
+	let celsius = (bytes[2] as u16) << 8 | bytes[3] as u16;
+	Ok((rh, decode_celsius(celsius)))
+}
+
+/// The highest bit of the temperature word is the sign.
+fn decode_celsius(word: u16) -> i16 {
+	let value = (word & 0x7fff) as i16;
+	if word & 0x8000 != 0 {
+		-value
+	} else {
+		value
+	}
+}
+
+fn input_config() -> Config {
+	let mut c = Config::default();
+	c.as_input();
+	c.with_bias(Bias::PullUp);
+	c
+}
+
+fn poll_edges(timeout: Duration, input: Request, line: u32) -> Result<Vec<(u64, EdgeKind)>, SensorError> {
+	input.reconfigure(&input_config())?;
+
+	let start = Instant::now();
+	let mut last_value = Value::Active;
+
+	let mut events = Vec::with_capacity(RESPONSE_EDGES);
+	while start.elapsed() < timeout && events.len() < RESPONSE_EDGES {
+		let new_value = input.value(line)?;
+		if new_value != last_value {
+			match new_value {
+				Value::Inactive => events.push((start.elapsed().as_micros() as u64, EdgeKind::Falling)),
+				Value::Active => events.push((start.elapsed().as_micros() as u64, EdgeKind::Rising)),
+			}
+			last_value = new_value;
+		}
+	}
+	if events.is_empty() {
+		return Err(SensorError::Timeout);
+	}
+	Ok(events)
+}
+
+fn edge_events(timeout: Duration, input: Request) -> Result<Vec<(u64, EdgeKind)>, SensorError> {
+	let mut config = input_config();
+	config.with_edge_detection(EdgeDetection::BothEdges);
+	input.reconfigure(&config)?;
+
+	let start = Instant::now();
+	let mut events = Vec::with_capacity(RESPONSE_EDGES);
+	while events.len() < RESPONSE_EDGES {
+		let Some(remaining) = timeout.checked_sub(start.elapsed()) else {
+			break;
+		};
+		if !input.wait_edge_event(remaining)? {
+			break;
+		}
+		let event = input.read_edge_event()?;
+		events.push((event.timestamp_ns / 1000, event.kind));
+	}
+	if events.is_empty() {
+		return Err(SensorError::Timeout);
+	}
+	Ok(events)
+}
+
+fn set_max_priority() {
+	unsafe {
+		let mut sched_para: libc::sched_param = std::mem::transmute([0u8; std::mem::size_of::<libc::sched_param>()]);
+		sched_para.sched_priority = libc::sched_get_priority_max(libc::SCHED_FIFO);
+		libc::sched_setscheduler(0, libc::SCHED_FIFO, (&sched_para) as *const libc::sched_param);
+	}
+}
+
+fn set_normal_priority() {
+	unsafe {
+		let sched_para: libc::sched_param = std::mem::transmute([0u8; std::mem::size_of::<libc::sched_param>()]);
+		libc::sched_setscheduler(0, libc::SCHED_OTHER, (&sched_para) as *const libc::sched_param);
+	}
+}
+
+#[test]
+fn test_process_data() {
+	let x = decode_bits(&[
+		0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 1, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 0,
+		1, 0, 1,
+	])
+	.unwrap();
+	assert_eq!(471, x.0);
+	assert_eq!(268, x.1);
+	assert_eq!(-101, decode_celsius(0x8065));
+	// a reading missing its last bit, with the response pulse in front
+	let x = decode_bits(&[
+		1, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 0, 1, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0,
+		0, 1, 1,
+	]);
+	assert!(matches!(x, Err(SensorError::MissingBits { bits: 39 })));
+}
+
+#[test]
+fn test_decode() {
+	// 65.2 %, -10.1 °C
+	let bytes = [0x02u8, 0x8c, 0x80, 0x65, 0x73];
+	let mut edges = vec![(0, EdgeKind::Falling), (80, EdgeKind::Rising), (160, EdgeKind::Falling)];
+	let mut t = 160;
+	for byte in bytes {
+		for bit in (0..8).rev() {
+			t += 50;
+			edges.push((t, EdgeKind::Rising));
+			t += if byte >> bit & 1 == 1 { 70 } else { 27 };
+			edges.push((t, EdgeKind::Falling));
+		}
+	}
+	assert_eq!(edges.len(), RESPONSE_EDGES);
+	assert_eq!(decode(&edges).unwrap(), (652, -101));
+
+	edges[50].0 += 50;
+	assert!(matches!(decode(&edges), Err(SensorError::ChecksumMismatch { .. })));
+	assert!(matches!(
+		decode(&edges[..60]),
+		Err(SensorError::MissingBits { bits: 29 })
+	));
+}
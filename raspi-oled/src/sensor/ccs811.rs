@@ -0,0 +1,155 @@
+//! CCS811 air quality sensor (eCO2 and TVOC) on I2C.
+
+use linux_embedded_hal::i2cdev::core::I2CDevice;
+use linux_embedded_hal::I2cdev;
+
+use super::{Reading, Sensor, SensorError};
+
+pub const CCS811_ADDR: u8 = 0x5A; // or 0x5B
+
+pub const CCS811_STATUS: u8 = 0x00;
+pub const CCS811_MEAS_MODE: u8 = 0x01;
+pub const CCS811_ALG_RESULT_DATA: u8 = 0x02;
+pub const CCS811_RAW_DATA: u8 = 0x03;
+pub const CCS811_ENV_DATA: u8 = 0x05;
+pub const CCS811_NTC: u8 = 0x06;
+pub const CCS811_THRESHOLDS: u8 = 0x10;
+pub const CCS811_BASELINE: u8 = 0x11;
+pub const CCS811_HW_ID: u8 = 0x20;
+pub const CCS811_HW_VERSION: u8 = 0x21;
+pub const CCS811_FW_BOOT_VERSION: u8 = 0x23;
+pub const CCS811_FW_APP_VERSION: u8 = 0x24;
+pub const CCS811_ERROR_ID: u8 = 0xE0;
+pub const CCS811_APP_START: u8 = 0xF4;
+pub const CCS811_SW_RESET: u8 = 0xFF;
+
+/// Status register: an error occurred, details in `CCS811_ERROR_ID`.
+const STATUS_ERROR: u8 = 1;
+/// Status register: a new reading is available.
+const STATUS_DATA_READY: u8 = 1 << 3;
+/// Status register: valid application firmware loaded.
+const STATUS_APP_VALID: u8 = 1 << 4;
+
+pub struct CCS811 {
+	i2c: I2cdev,
+	pub addr: u8,
+}
+
+impl CCS811 {
+	pub fn new(mut i2c: I2cdev, addr: u8) -> Result<Self, SensorError> {
+		i2c.set_slave_address(addr as u16)?;
+		Ok(Self { i2c, addr })
+	}
+
+	/// Open the sensor on the I2C bus at `path` (e.g. `/dev/i2c-1`).
+	pub fn open(path: &str, addr: u8) -> Result<Self, SensorError> {
+		CCS811::new(I2cdev::new(path)?, addr)
+	}
+
+	/// Returns the error code if the sensor reports an error.
+	pub fn check_for_error(&mut self) -> Result<Option<u8>, SensorError> {
+		let x = self.i2c.smbus_read_byte_data(CCS811_STATUS)?;
+		if (x & STATUS_ERROR) != 0 {
+			let err_code = self.i2c.smbus_read_byte_data(CCS811_ERROR_ID)?;
+			Ok(Some(err_code))
+		} else {
+			Ok(None)
+		}
+	}
+
+	/// Should be 0x81.
+	pub fn hardware_id(&mut self) -> Result<u8, SensorError> {
+		Ok(self.i2c.smbus_read_byte_data(CCS811_HW_ID)?)
+	}
+
+	pub fn app_valid(&mut self) -> Result<bool, SensorError> {
+		let x = self.i2c.smbus_read_byte_data(CCS811_STATUS)?;
+		Ok(x & STATUS_APP_VALID != 0)
+	}
+
+	/// Start the application firmware and select how often readings are taken.
+	pub fn set_drive_mode(&mut self, mode: CCS811DriveMode) -> Result<(), SensorError> {
+		self.i2c.smbus_write_byte(CCS811_APP_START)?;
+		if let Some(x) = self.check_for_error()? {
+			println!("error ignored {:b}", x);
+		}
+		let mut setting = self.i2c.smbus_read_byte_data(CCS811_MEAS_MODE)?;
+		setting &= !(0b00000111 << 4);
+		setting |= (mode as u8) << 4;
+		self.i2c.smbus_write_byte_data(CCS811_MEAS_MODE, setting)?;
+		Ok(())
+	}
+
+	pub fn get_baseline(&mut self) -> Result<u16, SensorError> {
+		let x = self.i2c.smbus_read_i2c_block_data(CCS811_BASELINE, 2)?;
+		Ok(((x[0] as u16) << 8) | (x[1] as u16))
+	}
+
+	/// Restore a baseline saved by [`CCS811::get_baseline`].
+	/// Should only be done after the sensor ran for 20 minutes.
+	pub fn set_baseline(&mut self, baseline: u16) -> Result<(), SensorError> {
+		self.i2c
+			.smbus_write_i2c_block_data(CCS811_BASELINE, &baseline.to_be_bytes())?;
+		Ok(())
+	}
+
+	/// Compensate readings for the current humidity (in %) and temperature (in °C).
+	pub fn set_env_data(&mut self, humidity: f64, celsius: f64) -> Result<(), SensorError> {
+		self.i2c
+			.smbus_write_i2c_block_data(CCS811_ENV_DATA, &env_data(humidity, celsius))?;
+		Ok(())
+	}
+
+	/// Returns (eCO2, tVOC)
+	pub fn get_reading(&mut self) -> Result<(u16, u16), SensorError> {
+		let x = self.i2c.smbus_read_i2c_block_data(CCS811_ALG_RESULT_DATA, 4)?;
+		Ok((
+			((x[0] as u16) << 8) | (x[1] as u16),
+			((x[2] as u16) << 8) | (x[3] as u16),
+		))
+	}
+}
+
+impl Sensor for CCS811 {
+	fn read(&mut self) -> Result<Reading, SensorError> {
+		if let Some(code) = self.check_for_error()? {
+			return Err(SensorError::Device(code));
+		}
+		let status = self.i2c.smbus_read_byte_data(CCS811_STATUS)?;
+		if status & STATUS_DATA_READY == 0 {
+			return Err(SensorError::NotReady);
+		}
+		let (eco2, tvoc) = self.get_reading()?;
+		Ok(Reading {
+			eco2: Some(eco2),
+			tvoc: Some(tvoc),
+			..Reading::default()
+		})
+	}
+}
+
+/// Encode `CCS811_ENV_DATA`: humidity and temperature (offset by 25 °C) in units of 1/512.
+fn env_data(humidity: f64, celsius: f64) -> [u8; 4] {
+	let humidity = (humidity.clamp(0.0, 100.0) * 512.0).round() as u16;
+	let temperature = ((celsius + 25.0).clamp(0.0, 127.0) * 512.0).round() as u16;
+	let [h0, h1] = humidity.to_be_bytes();
+	let [t0, t1] = temperature.to_be_bytes();
+	[h0, h1, t0, t1]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CCS811DriveMode {
+	Idle = 0,
+	EverySecond = 1,
+	Every10Seconds = 2,
+	Every60Seconds = 3,
+	/// Note the English manual states this is calculated every 10 ms!
+	Every250Milliseconds = 4,
+}
+
+#[test]
+fn test_env_data() {
+	// 48.5 % and 23.5 °C (+ 25 °C) both encode to 0x6100
+	assert_eq!(env_data(48.5, 23.5), [0x61, 0x00, 0x61, 0x00]);
+	assert_eq!(env_data(-3.0, -40.0), [0, 0, 0, 0]);
+}
@@ -0,0 +1,101 @@
+//! Sensor drivers behind a common [`Sensor`] trait.
+//!
+//! [`Simulated`] replays recorded readings, so everything reading sensors also runs without the hardware.
+
+use std::fmt;
+
+use linux_embedded_hal::i2cdev::linux::LinuxI2CError;
+
+mod am2302;
+pub use am2302::{Am2302, EdgeMode};
+pub mod ccs811;
+pub use ccs811::{CCS811DriveMode, CCS811, CCS811_ADDR};
+mod sampling;
+pub use sampling::{plausible, sample, ReadStats};
+mod simulated;
+pub use simulated::Simulated;
+
+pub trait Sensor {
+	/// Take a single reading. Quantities the sensor does not measure are `None`.
+	fn read(&mut self) -> Result<Reading, SensorError>;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Reading {
+	/// Relative humidity in 0.1 %.
+	pub humidity: Option<u16>,
+	/// Temperature in 0.1 °C.
+	pub celsius: Option<i16>,
+	/// Equivalent CO2 in ppm.
+	pub eco2: Option<u16>,
+	/// Total volatile organic compounds in ppb.
+	pub tvoc: Option<u16>,
+}
+
+#[derive(Debug)]
+pub enum SensorError {
+	Gpio(gpiocdev::Error),
+	I2c(LinuxI2CError),
+	Io(std::io::Error),
+	/// Checksum byte sent by the sensor and checksum of the received data.
+	ChecksumMismatch {
+		expected: u8,
+		actual: u8,
+	},
+	/// Humidity above 100 % (in 0.1 %).
+	HumidityTooHigh(u16),
+	/// Incomplete response, only `bits` of 40 data bits received.
+	MissingBits {
+		bits: usize,
+	},
+	/// The sensor did not respond at all.
+	Timeout,
+	/// No new data available yet.
+	NotReady,
+	/// Error code reported by the sensor.
+	Device(u8),
+	/// Invalid line in a replayed CSV file (1-based).
+	Csv {
+		line: usize,
+		message: String,
+	},
+}
+
+impl fmt::Display for SensorError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SensorError::Gpio(e) => write!(f, "GPIO error: {e}"),
+			SensorError::I2c(e) => write!(f, "I2C error: {e}"),
+			SensorError::Io(e) => write!(f, "{e}"),
+			SensorError::ChecksumMismatch { expected, actual } => {
+				write!(f, "checksum mismatch: expected {expected:#04x}, got {actual:#04x}")
+			},
+			SensorError::HumidityTooHigh(rh) => write!(f, "humidity out of range: {rh}"),
+			SensorError::MissingBits { bits } => write!(f, "incomplete response: {bits} of 40 bits"),
+			SensorError::Timeout => write!(f, "sensor did not respond in time"),
+			SensorError::NotReady => write!(f, "no new data available"),
+			SensorError::Device(code) => write!(f, "sensor reported error {code:#04x}"),
+			SensorError::Csv { line, message } => write!(f, "line {line}: {message}"),
+		}
+	}
+}
+
+impl std::error::Error for SensorError {}
+
+impl From<gpiocdev::Error> for SensorError {
+	fn from(error: gpiocdev::Error) -> Self {
+		SensorError::Gpio(error)
+	}
+}
+
+impl From<LinuxI2CError> for SensorError {
+	fn from(error: LinuxI2CError) -> Self {
+		SensorError::I2c(error)
+	}
+}
+
+impl From<std::io::Error> for SensorError {
+	fn from(error: std::io::Error) -> Self {
+		SensorError::Io(error)
+	}
+}
@@ -0,0 +1,132 @@
+//! Repeated reads combined into a single reading.
+
+use std::{fmt, thread::sleep, time::Duration};
+
+use super::{Reading, Sensor, SensorError};
+
+/// Largest accepted deviation from the median, in 0.1 °C.
+const MAX_CELSIUS_DEVIATION: i16 = 20;
+/// Largest accepted deviation from the median, in 0.1 %.
+const MAX_HUMIDITY_DEVIATION: u16 = 50;
+
+/// Outcome of the reads of one [`sample`] call.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReadStats {
+	pub attempts: u32,
+	/// Reads used for the result.
+	pub successes: u32,
+	pub checksum_failures: u32,
+	/// No or incomplete response.
+	pub timeouts: u32,
+	/// Implausible readings and outliers.
+	pub rejected: u32,
+	pub other_failures: u32,
+}
+
+impl ReadStats {
+	fn record_error(&mut self, error: &SensorError) {
+		match error {
+			SensorError::ChecksumMismatch { .. } => self.checksum_failures += 1,
+			SensorError::Timeout | SensorError::MissingBits { .. } => self.timeouts += 1,
+			SensorError::HumidityTooHigh(_) => self.rejected += 1,
+			_ => self.other_failures += 1,
+		}
+	}
+
+	pub fn success_rate(&self) -> f64 {
+		if self.attempts == 0 {
+			return 0.0;
+		}
+		self.successes as f64 / self.attempts as f64
+	}
+}
+
+impl fmt::Display for ReadStats {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{}/{} reads ok ({:.0} %), {} checksum failures, {} timeouts, {} rejected, {} other errors",
+			self.successes,
+			self.attempts,
+			self.success_rate() * 100.0,
+			self.checksum_failures,
+			self.timeouts,
+			self.rejected,
+			self.other_failures
+		)
+	}
+}
+
+/// Plausible AM2302 values: 0.1 to 100 % and -40 to 50 °C.
+pub fn plausible(reading: &Reading) -> bool {
+	reading.humidity.is_none_or(|x| x > 0 && x <= 1000) && reading.celsius.is_none_or(|x| (-400..500).contains(&x))
+}
+
+/// Read `sensor` until `samples` plausible readings are collected or `max_attempts` reads were made,
+/// waiting `interval` after every read.
+/// Returns the per-quantity median of the readings close to the median, `None` if there are none.
+pub fn sample(
+	sensor: &mut dyn Sensor,
+	samples: usize,
+	max_attempts: usize,
+	interval: Duration,
+) -> (Option<Reading>, ReadStats) {
+	let mut stats = ReadStats::default();
+	let mut readings = vec![];
+	while readings.len() < samples && (stats.attempts as usize) < max_attempts {
+		stats.attempts += 1;
+		match sensor.read() {
+			Ok(reading) if plausible(&reading) => readings.push(reading),
+			Ok(_) => stats.rejected += 1,
+			Err(e) => {
+				println!("error: {}", e);
+				stats.record_error(&e);
+			},
+		}
+		sleep(interval);
+	}
+	let center = median(&readings);
+	let (accepted, outliers): (Vec<Reading>, Vec<Reading>) = readings.into_iter().partition(|x| {
+		let close_celsius = match (x.celsius, center.celsius) {
+			(Some(a), Some(b)) => (a - b).abs() <= MAX_CELSIUS_DEVIATION,
+			_ => true,
+		};
+		let close_humidity = match (x.humidity, center.humidity) {
+			(Some(a), Some(b)) => a.abs_diff(b) <= MAX_HUMIDITY_DEVIATION,
+			_ => true,
+		};
+		close_celsius && close_humidity
+	});
+	stats.rejected += outliers.len() as u32;
+	stats.successes = accepted.len() as u32;
+	if accepted.is_empty() {
+		return (None, stats);
+	}
+	(Some(median(&accepted)), stats)
+}
+
+fn median(readings: &[Reading]) -> Reading {
+	fn of<T: Ord + Copy>(values: impl Iterator<Item = Option<T>>) -> Option<T> {
+		let mut values: Vec<T> = values.flatten().collect();
+		values.sort_unstable();
+		values.get(values.len() / 2).copied()
+	}
+	Reading {
+		humidity: of(readings.iter().map(|x| x.humidity)),
+		celsius: of(readings.iter().map(|x| x.celsius)),
+		eco2: of(readings.iter().map(|x| x.eco2)),
+		tvoc: of(readings.iter().map(|x| x.tvoc)),
+	}
+}
+
+#[test]
+fn test_sample() {
+	use super::Simulated;
+
+	let mut sensor =
+		Simulated::parse("humidity,celsius\n471,215\n0,215\n470,216\n468,298\n472,214\n469,215\n").unwrap();
+	let (reading, stats) = sample(&mut sensor, 5, 10, Duration::ZERO);
+	let reading = reading.unwrap();
+	assert_eq!((reading.humidity, reading.celsius), (Some(471), Some(215)));
+	assert_eq!((stats.attempts, stats.successes, stats.rejected), (6, 4, 2));
+}
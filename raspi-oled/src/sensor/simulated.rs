@@ -0,0 +1,117 @@
+//! Replays readings from a CSV file.
+//!
+//! The first line names the columns, any of `humidity`, `celsius`, `eco2` and `tvoc` in any order.
+//! Values use the units of [`Reading`]; empty fields are missing values. Lines starting with `#` are ignored.
+//!
+//! ```text
+//! humidity,celsius,eco2,tvoc
+//! 471,215,612,35
+//! 468,-12,,
+//! ```
+
+use std::{fs, path::Path, str::FromStr};
+
+use super::{Reading, Sensor, SensorError};
+
+pub struct Simulated {
+	readings: Vec<Reading>,
+	next: usize,
+}
+
+impl Simulated {
+	/// Replay `readings` in order, starting over at the end.
+	pub fn new(readings: Vec<Reading>) -> Self {
+		Simulated { readings, next: 0 }
+	}
+
+	pub fn from_csv(path: &Path) -> Result<Self, SensorError> {
+		Simulated::parse(&fs::read_to_string(path)?)
+	}
+
+	pub fn parse(csv: &str) -> Result<Self, SensorError> {
+		let mut lines = csv
+			.lines()
+			.enumerate()
+			.map(|(idx, line)| (idx + 1, line.trim()))
+			.filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+		let (header_line, header) = lines.next().ok_or(SensorError::Csv {
+			line: 1,
+			message: "missing header".to_owned(),
+		})?;
+		let columns: Vec<&str> = header.split(',').map(str::trim).collect();
+		if let Some(column) = columns
+			.iter()
+			.find(|x| !["humidity", "celsius", "eco2", "tvoc"].contains(x))
+		{
+			return Err(csv_error(header_line, format!("unknown column {column:?}")));
+		}
+
+		let mut readings = vec![];
+		for (line, values) in lines {
+			let values: Vec<&str> = values.split(',').map(str::trim).collect();
+			if values.len() != columns.len() {
+				return Err(csv_error(
+					line,
+					format!("expected {} fields, got {}", columns.len(), values.len()),
+				));
+			}
+			let mut reading = Reading::default();
+			for (&column, &value) in columns.iter().zip(&values) {
+				match column {
+					"humidity" => reading.humidity = parse_field(line, value)?,
+					"celsius" => reading.celsius = parse_field(line, value)?,
+					"eco2" => reading.eco2 = parse_field(line, value)?,
+					_ => reading.tvoc = parse_field(line, value)?,
+				}
+			}
+			readings.push(reading);
+		}
+		if readings.is_empty() {
+			return Err(csv_error(header_line, "no readings"));
+		}
+		Ok(Simulated::new(readings))
+	}
+}
+
+fn csv_error(line: usize, message: impl Into<String>) -> SensorError {
+	SensorError::Csv {
+		line,
+		message: message.into(),
+	}
+}
+
+fn parse_field<T: FromStr>(line: usize, value: &str) -> Result<Option<T>, SensorError> {
+	if value.is_empty() {
+		return Ok(None);
+	}
+	value
+		.parse()
+		.map(Some)
+		.map_err(|_| csv_error(line, format!("invalid value {value:?}")))
+}
+
+impl Sensor for Simulated {
+	fn read(&mut self) -> Result<Reading, SensorError> {
+		let reading = *self.readings.get(self.next).ok_or(SensorError::Timeout)?;
+		self.next = (self.next + 1) % self.readings.len();
+		Ok(reading)
+	}
+}
+
+#[test]
+fn test_simulated() {
+	let mut sensor =
+		Simulated::parse("# recorded in the kitchen\ncelsius, humidity,tvoc\n215,471,\n-12,468,35\n").unwrap();
+	let first = Reading {
+		humidity: Some(471),
+		celsius: Some(215),
+		..Reading::default()
+	};
+	assert_eq!(sensor.read().unwrap(), first);
+	assert_eq!(sensor.read().unwrap().tvoc, Some(35));
+	assert_eq!(sensor.read().unwrap(), first);
+
+	let err = Simulated::parse("celsius\n21.5\n").err().unwrap();
+	assert_eq!(err.to_string(), "line 2: invalid value \"21.5\"");
+	assert!(Simulated::parse("pressure\n1013\n").is_err());
+}
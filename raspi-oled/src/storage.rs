@@ -0,0 +1,442 @@
+//! Measurement storage in SQLite.
+//!
+//! Every row is one value of one [`Quantity`] measured by one sensor (e.g. `am2302` or a room name).
+//! Read statistics of every sampling run are stored next to the readings.
+//! Small values that have to survive a reboot (e.g. the CCS811 baseline) are kept in a key-value table.
+//! The schema version is kept in `PRAGMA user_version` and [`Storage::open`] migrates older databases,
+//! including the original `sensor_readings(time, humidity, celsius)` table.
+
+use std::{fmt, path::Path};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use time::OffsetDateTime;
+
+use crate::sensor::{ReadStats, Reading};
+
+/// Current schema version.
+pub const SCHEMA_VERSION: i32 = 3;
+
+/// Sensor id of readings taken by `take_measurement` unless told otherwise,
+/// and of readings migrated from the original table.
+pub const DEFAULT_SENSOR: &str = "am2302";
+
+/// Sensor id of the readings logged by the `ccs811` binary unless told otherwise.
+pub const AIR_SENSOR: &str = "ccs811";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+	Temperature,
+	Humidity,
+	Eco2,
+	Tvoc,
+}
+
+impl Quantity {
+	pub const ALL: [Quantity; 4] = [
+		Quantity::Temperature,
+		Quantity::Humidity,
+		Quantity::Eco2,
+		Quantity::Tvoc,
+	];
+
+	/// Name stored in the database.
+	pub fn name(self) -> &'static str {
+		match self {
+			Quantity::Temperature => "temperature",
+			Quantity::Humidity => "humidity",
+			Quantity::Eco2 => "eco2",
+			Quantity::Tvoc => "tvoc",
+		}
+	}
+
+	pub fn unit(self) -> &'static str {
+		match self {
+			Quantity::Temperature => "°C",
+			Quantity::Humidity => "%",
+			Quantity::Eco2 => "ppm",
+			Quantity::Tvoc => "ppb",
+		}
+	}
+
+	fn from_name(name: &str) -> Option<Self> {
+		Quantity::ALL.iter().copied().find(|x| x.name() == name)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+	Good = 0,
+	/// Plausible, but taken under bad conditions (e.g. while the sensor warms up).
+	Suspect = 1,
+	Invalid = 2,
+}
+
+impl Quality {
+	fn from_i64(x: i64) -> Self {
+		match x {
+			0 => Quality::Good,
+			1 => Quality::Suspect,
+			_ => Quality::Invalid,
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+	pub time: OffsetDateTime,
+	pub sensor: String,
+	pub quantity: Quantity,
+	/// Value in the unit of the quantity.
+	pub value: f64,
+	pub quality: Quality,
+}
+
+impl Measurement {
+	/// Value in tenths of the unit, as the AM2302 reports it.
+	pub fn tenths(&self) -> i32 {
+		(self.value * 10.0).round() as i32
+	}
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+	Sqlite(rusqlite::Error),
+	/// The database was written by a newer version.
+	UnsupportedVersion(i32),
+	/// Row with an invalid timestamp or quantity.
+	Corrupt(String),
+}
+
+impl fmt::Display for StorageError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			StorageError::Sqlite(e) => write!(f, "database error: {e}"),
+			StorageError::UnsupportedVersion(v) => {
+				write!(
+					f,
+					"database schema version {v} is newer than supported ({SCHEMA_VERSION})"
+				)
+			},
+			StorageError::Corrupt(message) => write!(f, "corrupt database: {message}"),
+		}
+	}
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+	fn from(error: rusqlite::Error) -> Self {
+		StorageError::Sqlite(error)
+	}
+}
+
+pub struct Storage {
+	database: Connection,
+}
+
+impl Storage {
+	/// Open (or create) the database at `path` and migrate it to [`SCHEMA_VERSION`].
+	pub fn open(path: &Path) -> Result<Self, StorageError> {
+		Storage::new(Connection::open(path)?)
+	}
+
+	pub fn new(database: Connection) -> Result<Self, StorageError> {
+		let storage = Storage { database };
+		storage.migrate()?;
+		Ok(storage)
+	}
+
+	pub fn version(&self) -> Result<i32, StorageError> {
+		Ok(self.database.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+	}
+
+	fn migrate(&self) -> Result<(), StorageError> {
+		let version = self.version()?;
+		if version > SCHEMA_VERSION {
+			return Err(StorageError::UnsupportedVersion(version));
+		}
+		if version == SCHEMA_VERSION {
+			return Ok(());
+		}
+		let tx = self.database.unchecked_transaction()?;
+		if version < 1 {
+			tx.execute_batch(
+				"
+				CREATE TABLE measurements(
+					time INTEGER NOT NULL,
+					sensor TEXT NOT NULL,
+					quantity TEXT NOT NULL,
+					value REAL NOT NULL,
+					unit TEXT NOT NULL,
+					quality INTEGER NOT NULL DEFAULT 0,
+					PRIMARY KEY (sensor, quantity, time)
+				);
+				CREATE INDEX measurements_time ON measurements(time);",
+			)?;
+			let legacy: bool = tx.query_row(
+				"SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'sensor_readings'",
+				[],
+				|row| row.get(0),
+			)?;
+			if legacy {
+				for (quantity, column) in [(Quantity::Temperature, "celsius"), (Quantity::Humidity, "humidity")] {
+					tx.execute(
+						&format!(
+							"INSERT INTO measurements (time, sensor, quantity, value, unit)
+							SELECT time, ?1, ?2, {column} / 10.0, ?3 FROM sensor_readings"
+						),
+						params![DEFAULT_SENSOR, quantity.name(), quantity.unit()],
+					)?;
+				}
+				tx.execute("DROP TABLE sensor_readings", [])?;
+			}
+		}
+		if version < 2 {
+			tx.execute_batch(
+				"
+				CREATE TABLE state(
+					key TEXT PRIMARY KEY,
+					value INTEGER NOT NULL,
+					time INTEGER NOT NULL
+				);",
+			)?;
+		}
+		if version < 3 {
+			tx.execute_batch(
+				"
+				CREATE TABLE read_stats(
+					time INTEGER NOT NULL,
+					sensor TEXT NOT NULL,
+					attempts INTEGER NOT NULL,
+					successes INTEGER NOT NULL,
+					checksum_failures INTEGER NOT NULL,
+					timeouts INTEGER NOT NULL,
+					rejected INTEGER NOT NULL,
+					other_failures INTEGER NOT NULL,
+					PRIMARY KEY (sensor, time)
+				);",
+			)?;
+		}
+		tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+		tx.commit()?;
+		Ok(())
+	}
+
+	pub fn insert(
+		&self,
+		time: OffsetDateTime,
+		sensor: &str,
+		quantity: Quantity,
+		value: f64,
+		quality: Quality,
+	) -> Result<(), StorageError> {
+		self.database.execute(
+			"INSERT OR REPLACE INTO measurements (time, sensor, quantity, value, unit, quality)
+			VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+			params![
+				time.unix_timestamp(),
+				sensor,
+				quantity.name(),
+				value,
+				quantity.unit(),
+				quality as i64
+			],
+		)?;
+		Ok(())
+	}
+
+	/// Store every quantity present in `reading`.
+	pub fn insert_reading(
+		&self,
+		time: OffsetDateTime,
+		sensor: &str,
+		reading: &Reading,
+		quality: Quality,
+	) -> Result<(), StorageError> {
+		let values = [
+			(Quantity::Temperature, reading.celsius.map(|x| x as f64 / 10.0)),
+			(Quantity::Humidity, reading.humidity.map(|x| x as f64 / 10.0)),
+			(Quantity::Eco2, reading.eco2.map(f64::from)),
+			(Quantity::Tvoc, reading.tvoc.map(f64::from)),
+		];
+		let tx = self.database.unchecked_transaction()?;
+		for (quantity, value) in values {
+			if let Some(value) = value {
+				self.insert(time, sensor, quantity, value, quality)?;
+			}
+		}
+		tx.commit()?;
+		Ok(())
+	}
+
+	/// Most recent value of `quantity` measured by `sensor`.
+	pub fn latest(&self, sensor: &str, quantity: Quantity) -> Result<Option<Measurement>, StorageError> {
+		Ok(self.recent(sensor, quantity, 1)?.pop())
+	}
+
+	/// Up to `limit` values of `quantity` measured by `sensor`, newest first.
+	pub fn recent(&self, sensor: &str, quantity: Quantity, limit: usize) -> Result<Vec<Measurement>, StorageError> {
+		let mut query = self.database.prepare_cached(
+			"SELECT time, sensor, quantity, value, quality FROM measurements
+			WHERE sensor = ?1 AND quantity = ?2 ORDER BY time DESC LIMIT ?3",
+		)?;
+		let rows = query.query_map(params![sensor, quantity.name(), limit as i64], |row| {
+			Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+		})?;
+		rows.map(|row| {
+			let (time, sensor, quantity, value, quality): (i64, String, String, f64, i64) = row?;
+			Ok(Measurement {
+				time: OffsetDateTime::from_unix_timestamp(time)
+					.map_err(|_| StorageError::Corrupt(format!("invalid timestamp {time}")))?,
+				sensor,
+				quantity: Quantity::from_name(&quantity)
+					.ok_or_else(|| StorageError::Corrupt(format!("unknown quantity {quantity:?}")))?,
+				value,
+				quality: Quality::from_i64(quality),
+			})
+		})
+		.collect()
+	}
+
+	/// Time of the most recent measurement of any sensor.
+	pub fn latest_time(&self) -> Result<Option<OffsetDateTime>, StorageError> {
+		let time: Option<i64> = self
+			.database
+			.query_row("SELECT MAX(time) FROM measurements", [], |row| row.get(0))
+			.optional()?
+			.flatten();
+		time.map(|time| {
+			OffsetDateTime::from_unix_timestamp(time)
+				.map_err(|_| StorageError::Corrupt(format!("invalid timestamp {time}")))
+		})
+		.transpose()
+	}
+
+	pub fn insert_stats(&self, time: OffsetDateTime, sensor: &str, stats: &ReadStats) -> Result<(), StorageError> {
+		self.database.execute(
+			"INSERT OR REPLACE INTO read_stats
+			(time, sensor, attempts, successes, checksum_failures, timeouts, rejected, other_failures)
+			VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+			params![
+				time.unix_timestamp(),
+				sensor,
+				stats.attempts,
+				stats.successes,
+				stats.checksum_failures,
+				stats.timeouts,
+				stats.rejected,
+				stats.other_failures
+			],
+		)?;
+		Ok(())
+	}
+
+	/// Sum of the read statistics of `sensor` stored since `since`.
+	pub fn stats_since(&self, sensor: &str, since: OffsetDateTime) -> Result<ReadStats, StorageError> {
+		Ok(self.database.query_row(
+			"SELECT
+				COALESCE(SUM(attempts), 0), COALESCE(SUM(successes), 0), COALESCE(SUM(checksum_failures), 0),
+				COALESCE(SUM(timeouts), 0), COALESCE(SUM(rejected), 0), COALESCE(SUM(other_failures), 0)
+			FROM read_stats WHERE sensor = ?1 AND time >= ?2",
+			params![sensor, since.unix_timestamp()],
+			|row| {
+				Ok(ReadStats {
+					attempts: row.get(0)?,
+					successes: row.get(1)?,
+					checksum_failures: row.get(2)?,
+					timeouts: row.get(3)?,
+					rejected: row.get(4)?,
+					other_failures: row.get(5)?,
+				})
+			},
+		)?)
+	}
+
+	/// Value stored under `key` and when it was stored.
+	pub fn state(&self, key: &str) -> Result<Option<(i64, OffsetDateTime)>, StorageError> {
+		let row: Option<(i64, i64)> = self
+			.database
+			.query_row("SELECT value, time FROM state WHERE key = ?1", [key], |row| {
+				Ok((row.get(0)?, row.get(1)?))
+			})
+			.optional()?;
+		row.map(|(value, time)| {
+			let time = OffsetDateTime::from_unix_timestamp(time)
+				.map_err(|_| StorageError::Corrupt(format!("invalid timestamp {time}")))?;
+			Ok((value, time))
+		})
+		.transpose()
+	}
+
+	pub fn set_state(&self, key: &str, value: i64, time: OffsetDateTime) -> Result<(), StorageError> {
+		self.database.execute(
+			"INSERT OR REPLACE INTO state (key, value, time) VALUES (?1, ?2, ?3)",
+			params![key, value, time.unix_timestamp()],
+		)?;
+		Ok(())
+	}
+
+	/// Ids of all sensors with stored measurements.
+	pub fn sensors(&self) -> Result<Vec<String>, StorageError> {
+		let mut query = self
+			.database
+			.prepare("SELECT DISTINCT sensor FROM measurements ORDER BY sensor")?;
+		let sensors = query.query_map([], |row| row.get(0))?;
+		Ok(sensors.collect::<Result<_, _>>()?)
+	}
+}
+
+#[test]
+fn test_migration() {
+	let database = Connection::open_in_memory().unwrap();
+	database
+		.execute_batch(
+			"
+			CREATE TABLE sensor_readings(time INTEGER PRIMARY KEY, humidity INTEGER NOT NULL, celsius INTEGER NOT NULL);
+			INSERT INTO sensor_readings VALUES (1000, 471, 215), (1300, 468, 217);",
+		)
+		.unwrap();
+	let storage = Storage::new(database).unwrap();
+	assert_eq!(storage.version().unwrap(), SCHEMA_VERSION);
+	let temps = storage.recent(DEFAULT_SENSOR, Quantity::Temperature, 10).unwrap();
+	assert_eq!(temps.iter().map(Measurement::tenths).collect::<Vec<_>>(), [217, 215]);
+	assert_eq!(
+		storage
+			.latest(DEFAULT_SENSOR, Quantity::Humidity)
+			.unwrap()
+			.unwrap()
+			.value,
+		46.8
+	);
+
+	let time = OffsetDateTime::from_unix_timestamp(1600).unwrap();
+	let reading = Reading {
+		eco2: Some(612),
+		..Reading::default()
+	};
+	storage
+		.insert_reading(time, "kitchen", &reading, Quality::Suspect)
+		.unwrap();
+	assert_eq!(storage.latest_time().unwrap(), Some(time));
+	assert_eq!(storage.sensors().unwrap(), ["am2302", "kitchen"]);
+	let eco2 = storage.latest("kitchen", Quantity::Eco2).unwrap().unwrap();
+	assert_eq!((eco2.value, eco2.quality), (612.0, Quality::Suspect));
+	assert!(storage.latest("kitchen", Quantity::Temperature).unwrap().is_none());
+
+	storage.set_state("ccs811_baseline", 0x847b, time).unwrap();
+	assert_eq!(storage.state("ccs811_baseline").unwrap(), Some((0x847b, time)));
+
+	let stats = ReadStats {
+		attempts: 7,
+		successes: 5,
+		checksum_failures: 2,
+		..ReadStats::default()
+	};
+	storage.insert_stats(time, DEFAULT_SENSOR, &stats).unwrap();
+	storage
+		.insert_stats(time + time::Duration::minutes(5), DEFAULT_SENSOR, &stats)
+		.unwrap();
+	let total = storage.stats_since(DEFAULT_SENSOR, time).unwrap();
+	assert_eq!((total.attempts, total.successes, total.checksum_failures), (14, 10, 4));
+	assert_eq!(storage.stats_since("kitchen", time).unwrap(), ReadStats::default());
+}
@@ -0,0 +1,493 @@
+//! Calendar sync used by `refresh_json`: fetches all configured sources and merges them into one events file.
+//!
+//! Responses are cached with their `ETag` and `Last-Modified` headers, so unchanged calendars are not downloaded
+//! again. If a source cannot be reached, its cached response is used instead.
+
+use std::{
+	fmt, fs, io,
+	path::{Path, PathBuf},
+	time::Duration as StdDuration,
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+use time_tz::Tz;
+use ureq::{
+	http::{self, Method},
+	Agent,
+};
+
+use crate::{
+	events::{EventsError, ICAL_DAYS},
+	ical::{self, IcalError},
+	Events,
+};
+
+/// Requests all events of a CalDAV collection.
+const CALENDAR_QUERY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+	<d:prop><c:calendar-data/></d:prop>
+	<c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT"/></c:comp-filter></c:filter>
+</c:calendar-query>
+"#;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Source {
+	/// JSON array of events, as served by the event alerts endpoint.
+	Json { url: String },
+	/// iCalendar file.
+	Ics { url: String },
+	/// CalDAV calendar collection, using basic authentication if `username` is set.
+	Caldav {
+		url: String,
+		username: Option<String>,
+		/// Environment variable containing the password.
+		password_env: Option<String>,
+	},
+	/// Local events JSON, iCalendar file or directory, see [`Events::load`].
+	File { path: PathBuf },
+}
+
+impl Source {
+	fn url(&self) -> Option<&str> {
+		match self {
+			Source::Json { url } | Source::Ics { url } | Source::Caldav { url, .. } => Some(url),
+			Source::File { .. } => None,
+		}
+	}
+
+	fn describe(&self) -> String {
+		match self {
+			Source::File { path } => path.display().to_string(),
+			_ => self.url().unwrap().to_owned(),
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum SyncError {
+	/// The source could not be reached and was never fetched before.
+	Http(String, ureq::Error),
+	Status(String, u16),
+	Json(String, serde_json::Error),
+	Ical(String, IcalError),
+	Events(EventsError),
+	/// Missing environment variable of a CalDAV password.
+	Password(String),
+	Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for SyncError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			SyncError::Http(url, e) => write!(f, "failed to fetch {url}: {e}"),
+			SyncError::Status(url, status) => write!(f, "failed to fetch {url}: HTTP status {status}"),
+			SyncError::Json(url, e) => write!(f, "failed to parse {url}: {e}"),
+			SyncError::Ical(url, e) => write!(f, "failed to parse {url}: {e}"),
+			SyncError::Events(e) => write!(f, "{e}"),
+			SyncError::Password(var) => write!(f, "environment variable {var} is not set"),
+			SyncError::Io(path, e) => write!(f, "failed to write {}: {e}", path.display()),
+		}
+	}
+}
+
+impl std::error::Error for SyncError {}
+
+impl From<EventsError> for SyncError {
+	fn from(e: EventsError) -> Self {
+		SyncError::Events(e)
+	}
+}
+
+/// Validators of a cached response.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheMeta {
+	etag: Option<String>,
+	last_modified: Option<String>,
+}
+
+pub struct Sync {
+	sources: Vec<Source>,
+	/// Directory of the cached responses.
+	cache: PathBuf,
+	agent: Agent,
+}
+
+impl Sync {
+	pub fn new(sources: Vec<Source>, cache: PathBuf) -> Self {
+		let agent = Agent::config_builder()
+			.allow_non_standard_methods(true)
+			.http_status_as_error(false)
+			.timeout_global(Some(StdDuration::from_secs(30)))
+			.build()
+			.new_agent();
+		Sync { sources, cache, agent }
+	}
+
+	/// Fetch and merge all sources, expanding recurring events for the [`ICAL_DAYS`] days after `now`.
+	/// Events found in multiple sources are only included once.
+	pub fn fetch(&self, tz: &'static Tz, now: OffsetDateTime) -> Result<Events, SyncError> {
+		let mut merged = Events::default();
+		for source in &self.sources {
+			let events = self.fetch_source(source, tz, now)?;
+			merged.events.extend(events.events);
+			merged.weekly.extend(events.weekly);
+		}
+		merged
+			.events
+			.sort_by(|a, b| (&a.start_time, &a.name).cmp(&(&b.start_time, &b.name)));
+		merged
+			.events
+			.dedup_by(|a, b| (&a.name, &a.start_time, &a.end_time) == (&b.name, &b.start_time, &b.end_time));
+		Ok(merged)
+	}
+
+	fn fetch_source(&self, source: &Source, tz: &'static Tz, now: OffsetDateTime) -> Result<Events, SyncError> {
+		let name = source.describe();
+		let to = now + Duration::days(ICAL_DAYS);
+		let events = match source {
+			Source::File { path } => return Ok(Events::load(path, tz, now)?),
+			Source::Json { .. } => {
+				let body = self.download(source)?;
+				serde_json::from_str(&body).map_err(|e| SyncError::Json(name, e))?
+			},
+			Source::Ics { .. } => {
+				let body = self.download(source)?;
+				ical::parse(&body, tz, now, to).map_err(|e| SyncError::Ical(name, e))?
+			},
+			Source::Caldav { .. } => {
+				let body = self.download(source)?;
+				let mut events = vec![];
+				for calendar in calendar_data(&body) {
+					events.extend(ical::parse(&calendar, tz, now, to).map_err(|e| SyncError::Ical(name.clone(), e))?);
+				}
+				events
+			},
+		};
+		Ok(Events {
+			events,
+			..Events::default()
+		})
+	}
+
+	/// Body of the response for `source`, using the cache if unchanged or unreachable.
+	fn download(&self, source: &Source) -> Result<String, SyncError> {
+		let url = source.url().unwrap();
+		let path = self.cache.join(cache_name(url));
+		let body_path = path.with_extension("body");
+		let meta_path = path.with_extension("json");
+		let cached = fs::read_to_string(&body_path).ok();
+		let meta: CacheMeta = match cached {
+			Some(_) => fs::read(&meta_path)
+				.ok()
+				.and_then(|x| serde_json::from_slice(&x).ok())
+				.unwrap_or_default(),
+			None => CacheMeta::default(),
+		};
+		let fallback = |e: SyncError| match &cached {
+			Some(body) => {
+				eprintln!("warning: {e}, using the cached response");
+				Ok(body.clone())
+			},
+			None => Err(e),
+		};
+		let mut response = match self.request(source, &meta) {
+			Ok(response) => response,
+			Err(e) => return fallback(e),
+		};
+		let status = response.status().as_u16();
+		if status == 304 {
+			if let Some(body) = &cached {
+				return Ok(body.clone());
+			}
+		}
+		if !(200..300).contains(&status) {
+			return fallback(SyncError::Status(url.to_owned(), status));
+		}
+		let header = |name| {
+			response
+				.headers()
+				.get(name)
+				.and_then(|x: &http::HeaderValue| x.to_str().ok())
+				.map(str::to_owned)
+		};
+		let meta = CacheMeta {
+			etag: header(http::header::ETAG),
+			last_modified: header(http::header::LAST_MODIFIED),
+		};
+		let body = match response.body_mut().read_to_string() {
+			Ok(body) => body,
+			Err(e) => return fallback(SyncError::Http(url.to_owned(), e)),
+		};
+		let io_error = |path: &Path| {
+			let path = path.to_owned();
+			move |e| SyncError::Io(path, e)
+		};
+		fs::create_dir_all(&self.cache).map_err(io_error(&self.cache))?;
+		write_atomic(&body_path, body.as_bytes()).map_err(io_error(&body_path))?;
+		let meta = serde_json::to_vec(&meta).unwrap();
+		write_atomic(&meta_path, &meta).map_err(io_error(&meta_path))?;
+		Ok(body)
+	}
+
+	fn request(&self, source: &Source, meta: &CacheMeta) -> Result<http::Response<ureq::Body>, SyncError> {
+		let url = source.url().unwrap();
+		let mut request = http::Request::builder().uri(url);
+		if let Some(etag) = &meta.etag {
+			request = request.header(http::header::IF_NONE_MATCH, etag);
+		}
+		if let Some(last_modified) = &meta.last_modified {
+			request = request.header(http::header::IF_MODIFIED_SINCE, last_modified);
+		}
+		let http_error = |e| SyncError::Http(url.to_owned(), e);
+		if let Source::Caldav {
+			username, password_env, ..
+		} = source
+		{
+			if let Some(username) = username {
+				let password = match password_env {
+					Some(var) => std::env::var(var).map_err(|_| SyncError::Password(var.clone()))?,
+					None => String::new(),
+				};
+				let credentials = BASE64_STANDARD.encode(format!("{username}:{password}"));
+				request = request.header(http::header::AUTHORIZATION, format!("Basic {credentials}"));
+			}
+			let request = request
+				.method(Method::from_bytes(b"REPORT").unwrap())
+				.header("Depth", "1")
+				.header(http::header::CONTENT_TYPE, "application/xml; charset=utf-8")
+				.body(CALENDAR_QUERY)
+				.map_err(|e| http_error(e.into()))?;
+			return self.agent.run(request).map_err(http_error);
+		}
+		let request = request.body(()).map_err(|e| http_error(e.into()))?;
+		self.agent.run(request).map_err(http_error)
+	}
+}
+
+/// File name of the cached response of `url`.
+fn cache_name(url: &str) -> String {
+	let readable: String = url
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+		.take(64)
+		.collect();
+	// FNV-1a, to tell apart long URLs with the same prefix
+	let hash = url.bytes().fold(0xcbf29ce484222325u64, |hash, x| {
+		(hash ^ x as u64).wrapping_mul(0x100000001b3)
+	});
+	format!("{readable}_{hash:016x}")
+}
+
+/// Contents of all `calendar-data` elements of a CalDAV multistatus response.
+fn calendar_data(xml: &str) -> Vec<String> {
+	let mut calendars = vec![];
+	let mut rest = xml;
+	while let Some(start) = rest.find('<') {
+		rest = &rest[start + 1..];
+		let end = match rest.find('>') {
+			Some(end) => end,
+			None => break,
+		};
+		let tag = &rest[..end];
+		let name = tag.split_whitespace().next().unwrap_or("");
+		let local_name = name.rsplit(':').next().unwrap();
+		rest = &rest[end + 1..];
+		if local_name != "calendar-data" || tag.ends_with('/') {
+			continue;
+		}
+		let close = format!("</{name}>");
+		let content = match rest.find(&close) {
+			Some(x) => &rest[..x],
+			None => break,
+		};
+		rest = &rest[content.len() + close.len()..];
+		calendars.push(match content.trim().strip_prefix("<![CDATA[") {
+			Some(cdata) => cdata.trim_end().trim_end_matches("]]>").to_owned(),
+			None => unescape_xml(content),
+		});
+	}
+	calendars
+}
+
+fn unescape_xml(text: &str) -> String {
+	let mut result = String::with_capacity(text.len());
+	let mut rest = text;
+	while let Some(start) = rest.find('&') {
+		result += &rest[..start];
+		rest = &rest[start..];
+		let end = match rest.find(';') {
+			Some(end) => end,
+			None => break,
+		};
+		let entity = &rest[1..end];
+		let c = match entity {
+			"lt" => Some('<'),
+			"gt" => Some('>'),
+			"amp" => Some('&'),
+			"quot" => Some('"'),
+			"apos" => Some('\''),
+			_ => entity
+				.strip_prefix("#x")
+				.map(|x| u32::from_str_radix(x, 16))
+				.or_else(|| entity.strip_prefix('#').map(str::parse))
+				.and_then(Result::ok)
+				.and_then(char::from_u32),
+		};
+		match c {
+			Some(c) => {
+				result.push(c);
+				rest = &rest[end + 1..];
+			},
+			None => {
+				result.push('&');
+				rest = &rest[1..];
+			},
+		}
+	}
+	result + rest
+}
+
+/// Replace `path` with `contents`, so readers never see a partially written file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+	let mut tmp = path.as_os_str().to_owned();
+	tmp.push(".tmp");
+	fs::write(&tmp, contents)?;
+	fs::rename(&tmp, path)
+}
+
+/// Write `events` as JSON readable by [`Events::load`].
+pub fn write_events(path: &Path, events: &Events) -> io::Result<()> {
+	write_atomic(path, &serde_json::to_vec_pretty(events).unwrap())
+}
+
+/// Local HTTP server answering one request per response, returns its URL and the received requests.
+#[cfg(test)]
+fn serve(responses: Vec<String>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+	use std::io::{BufRead, BufReader, Read, Write};
+
+	let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+	let url = format!("http://{}", listener.local_addr().unwrap());
+	let server = std::thread::spawn(move || {
+		let mut requests = vec![];
+		for response in responses {
+			let (stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(stream);
+			let mut request = String::new();
+			let mut length = 0;
+			loop {
+				let mut line = String::new();
+				reader.read_line(&mut line).unwrap();
+				if let Some(x) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+					length = x.trim().parse().unwrap();
+				}
+				request += &line;
+				if line == "\r\n" {
+					break;
+				}
+			}
+			let mut body = vec![0; length];
+			reader.read_exact(&mut body).unwrap();
+			request += &String::from_utf8(body).unwrap();
+			requests.push(request);
+			let mut stream = reader.into_inner();
+			stream.write_all(response.as_bytes()).unwrap();
+		}
+		requests
+	});
+	(url, server)
+}
+
+#[test]
+fn test_sync() {
+	let tz = raspi_lib::DEFAULT_TIMEZONE;
+	let now = raspi_lib::local_time(
+		time::Date::from_calendar_date(2024, time::Month::May, 1).unwrap(),
+		time::Time::MIDNIGHT,
+		tz,
+	);
+	let response = |status: &str, headers: &str, body: &str| {
+		format!(
+			"HTTP/1.1 {status}\r\nConnection: close\r\n{headers}Content-Length: {}\r\n\r\n{body}",
+			body.len()
+		)
+	};
+	let ics = response(
+		"200 OK",
+		"ETag: \"v1\"\r\n",
+		"BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Dentist\r\nDTSTART:20240502T080000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+	);
+	let json = response(
+		"200 OK",
+		"",
+		r#"[{"name":"Dentist","startTime":"2024-05-02T10:00:00"},{"name":"Bins","startTime":"2024-05-03T07:00:00"}]"#,
+	);
+	let caldav = response(
+		"207 Multi-Status",
+		"",
+		"<d:multistatus xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\"><d:response><d:propstat><d:prop>\
+		<c:calendar-data>BEGIN:VEVENT&#13;\nSUMMARY:Tom &amp; Jerry&#13;\nDTSTART:20240504T120000&#13;\nEND:VEVENT&#13;\n\
+		</c:calendar-data></d:prop></d:propstat></d:response></d:multistatus>",
+	);
+	let not_modified = "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_owned();
+	let error = response("503 Service Unavailable", "", "");
+	let (url, server) = serve(vec![ics, json, caldav, not_modified, error.clone(), error]);
+
+	let cache = std::env::temp_dir().join(format!("raspi-oled-sync-{}", std::process::id()));
+	let _ = fs::remove_dir_all(&cache);
+	let sources = vec![
+		Source::Ics {
+			url: format!("{url}/cal.ics"),
+		},
+		Source::Json {
+			url: format!("{url}/event_alerts"),
+		},
+		Source::Caldav {
+			url: format!("{url}/dav/"),
+			username: Some("me".to_owned()),
+			password_env: None,
+		},
+	];
+	let sync = Sync::new(sources, cache.clone());
+	let names = |events: &Events| -> Vec<(String, String)> {
+		events
+			.events
+			.iter()
+			.map(|x| (x.name.clone(), x.start_time.clone()))
+			.collect()
+	};
+	let expected = [
+		("Dentist", "2024-05-02T10:00:00"),
+		("Bins", "2024-05-03T07:00:00"),
+		("Tom & Jerry", "2024-05-04T12:00:00"),
+	]
+	.iter()
+	.map(|&(a, b)| (a.to_owned(), b.to_owned()))
+	.collect::<Vec<_>>();
+	assert_eq!(names(&sync.fetch(tz, now).unwrap()), expected);
+
+	// unchanged, unreachable and never fetched
+	let sync = Sync::new(sync.sources[..2].to_vec(), cache.clone());
+	assert_eq!(names(&sync.fetch(tz, now).unwrap()), expected[..2]);
+	let sync = Sync::new(
+		vec![Source::Ics {
+			url: format!("{url}/other.ics"),
+		}],
+		cache.clone(),
+	);
+	assert!(matches!(sync.fetch(tz, now), Err(SyncError::Status(_, 503))));
+
+	let requests = server.join().unwrap();
+	assert!(requests[2].starts_with("REPORT /dav/ "));
+	assert!(requests[2]
+		.to_ascii_lowercase()
+		.contains("authorization: basic bwu6\r\n"));
+	assert!(requests[3].to_ascii_lowercase().contains("if-none-match: \"v1\"\r\n"));
+
+	let path = cache.join("events.json");
+	write_events(&path, &Events::default()).unwrap();
+	assert!(Events::load(&path, tz, now).unwrap().events.is_empty());
+	let _ = fs::remove_dir_all(&cache);
+}